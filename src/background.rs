@@ -0,0 +1,66 @@
+use color::Color;
+use vector::Vector3;
+use light::EnvironmentMap;
+
+/// What rays see when they miss every object in the scene
+#[derive(Debug, Clone)]
+pub enum Background {
+    /// The same color in every direction
+    Color(Color),
+
+    /// A blend from `bottom`, straight down, to `top`, straight up
+    Gradient {
+        bottom: Color,
+        top: Color
+    },
+
+    Environment(EnvironmentMap),
+
+    /// The absolute value of each component of the direction as red, green and blue, times
+    /// the brightness. Only the camera sees it, so it neither shows up in reflections nor
+    /// lights the scene. This is the default.
+    AbsoluteDirection {
+        brightness: f64
+    },
+
+    /// Nothing at all. Camera rays that miss every object leave the image transparent.
+    Transparent,
+}
+
+
+impl Background {
+    /// Get the color in a direction
+    pub fn color(&self, direction: Vector3) -> Color {
+        match self {
+            Background::Color(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.normal().y + 1.0);
+                bottom.apply_brightness(1.0 - t) + top.apply_brightness(t)
+            },
+            Background::Environment(map) => map.color(direction),
+            Background::AbsoluteDirection { brightness } => {
+                let direction = direction.normal();
+                Color::new(direction.x.abs(), direction.y.abs(), direction.z.abs())
+                    .apply_brightness(*brightness)
+            },
+            Background::Transparent => Color::black(),
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        matches!(self, Background::Transparent)
+    }
+
+    /// Whether rays other than those from the camera see the background
+    pub fn is_reflected(&self) -> bool {
+        !matches!(self, Background::AbsoluteDirection { .. })
+    }
+}
+
+impl Default for Background {
+    fn default() -> Background {
+        Background::AbsoluteDirection {
+            brightness: 0.25,
+        }
+    }
+}
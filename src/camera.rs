@@ -0,0 +1,50 @@
+use vector::Vector3;
+use ray::Ray;
+
+/// A pinhole camera looking along the positive z-axis
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub origin: Vector3,
+
+    /// The vertical field of view, in degrees
+    pub field_of_view: f64
+}
+
+
+impl Camera {
+    // Hjälp med den linjära algebran: https://www.scratchapixel
+    // .com/lessons/3d-basic-rendering/ray-tracing-generating-camera-rays
+    // /generating-camera-rays
+    pub fn ray(&self, x: u32, y: u32, width: u32, height: u32) -> Ray {
+        let fov = self.field_of_view.to_radians();
+        let aspect_ratio = (width as f64) / (height as f64);
+
+        let normal_x = 2.0 * (x as f64) / (width as f64) - 1.0;
+        let normal_y = 1.0 - 2.0 * (y as f64) / (height as f64);
+
+        let world_height = (fov / 2.0).tan();
+
+        let direction_x = normal_x * aspect_ratio * world_height;
+        let direction_y = normal_y * world_height;
+
+        let direction = Vector3 {
+            x: direction_x,
+            y: direction_y,
+            z: 1.0,
+        };
+
+        Ray {
+            origin: self.origin,
+            direction: direction.normal()
+        }
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera {
+            origin: Vector3::new(0.0, 0.0, -1.0),
+            field_of_view: 70.0,
+        }
+    }
+}
@@ -1,6 +1,10 @@
-use image::Rgba;
+use image::{
+    Rgb,
+    Rgba
+};
 
 use std::fmt;
+use std::ops;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Color {
@@ -32,6 +36,47 @@ impl Color {
         }
     }
 
+    /// Convert from the CIE xyY color space to linear sRGB
+    // https://www.wikiwand.com/en/SRGB
+    pub fn from_xyy(x: f64, y: f64, luminance: f64) -> Color {
+        if y <= 0.0 {
+            return Color::black();
+        }
+
+        let big_x = x * luminance / y;
+        let big_y = luminance;
+        let big_z = (1.0 - x - y) * luminance / y;
+
+        Color::new(
+            (3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z).max(0.0),
+            (-0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z).max(0.0),
+            (0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z).max(0.0)
+        )
+    }
+
+    /// The color of a black body at a temperature in kelvin, with a luminance of 1. The
+    /// temperature is limited to between 1667 K and 25000 K.
+    // https://www.wikiwand.com/en/Planckian_locus#/Approximation
+    pub fn from_temperature(kelvin: f64) -> Color {
+        let t = kelvin.clamp(1667.0, 25000.0);
+
+        let x = if t <= 4000.0 {
+            -0.2661239e9 / t.powi(3) - 0.2343589e6 / t.powi(2) + 0.8776956e3 / t + 0.179910
+        } else {
+            -3.0258469e9 / t.powi(3) + 2.1070379e6 / t.powi(2) + 0.2226347e3 / t + 0.240390
+        };
+
+        let y = if t <= 2222.0 {
+            -1.1063814 * x.powi(3) - 1.34811020 * x.powi(2) + 2.18555832 * x - 0.20219683
+        } else if t <= 4000.0 {
+            -0.9549476 * x.powi(3) - 1.37418593 * x.powi(2) + 2.09137015 * x - 0.16748867
+        } else {
+            3.0817580 * x.powi(3) - 5.87338670 * x.powi(2) + 3.75112997 * x - 0.37001483
+        };
+
+        Color::from_xyy(x, y, 1.0)
+    }
+
     pub fn apply_brightness(mut self, brightness: f64) -> Color {
         self.r *= brightness;
         self.g *= brightness;
@@ -40,12 +85,13 @@ impl Color {
         self
     }
 
-    pub fn add(mut self, other: Color) -> Color {
-        self.r += other.r;
-        self.g += other.g;
-        self.b += other.b;
-
-        self
+    /// Blend towards another color, from this color at 0 to the other at 1
+    pub fn mix(self, other: Color, t: f64) -> Color {
+        Color {
+            r: self.r + t * (other.r - self.r),
+            g: self.g + t * (other.g - self.g),
+            b: self.b + t * (other.b - self.b),
+        }
     }
 
     pub fn multiply(mut self, other: Color) -> Color {
@@ -55,16 +101,51 @@ impl Color {
 
         self
     }
+
+    // https://www.wikiwand.com/en/Relative_luminance
+    pub fn luminance(self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Convert to a pixel with an alpha between 0 for transparent and 1 for opaque. The color
+    /// is not multiplied by its alpha.
+    pub fn to_rgba(self, alpha: f64) -> Rgba<u8> {
+        Rgba {
+            data: [
+                float_to_byte_color(self.r),
+                float_to_byte_color(self.g),
+                float_to_byte_color(self.b),
+                float_to_byte_color(alpha)
+            ]
+        }
+    }
+}
+
+impl ops::Add<Color> for Color {
+    type Output = Color;
+
+    fn add(mut self, other: Color) -> Color {
+        self.r += other.r;
+        self.g += other.g;
+        self.b += other.b;
+
+        self
+    }
 }
 
-impl Into<[u8; 4]> for Color {
-    fn into(self) -> [u8; 4] {
-        [
-            float_to_byte_color(self.r),
-            float_to_byte_color(self.g),
-            float_to_byte_color(self.b),
-            255
-        ]
+impl From<Rgb<u8>> for Color {
+    fn from(pixel: Rgb<u8>) -> Color {
+        Color {
+            r: f64::from(pixel.data[0]) / 255.0,
+            g: f64::from(pixel.data[1]) / 255.0,
+            b: f64::from(pixel.data[2]) / 255.0,
+        }
+    }
+}
+
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> [u8; 4] {
+        color.to_rgba(1.0).data
     }
 }
 
@@ -78,11 +159,10 @@ fn float_to_byte_color(float: f64) -> u8 {
     }
 }
 
-impl Into<Rgba<u8>> for Color {
-    fn into(self) -> Rgba<u8> {
-        Rgba {
-            data: self.into()
-        }
+/// Opaque colors
+impl From<Color> for Rgba<u8> {
+    fn from(color: Color) -> Rgba<u8> {
+        color.to_rgba(1.0)
     }
 }
 
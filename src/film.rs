@@ -0,0 +1,387 @@
+use image::{
+    DynamicImage,
+    GenericImage
+};
+
+use color::Color;
+use tile::Tile;
+
+use std::fs::{self, File};
+use std::io::{
+    self,
+    Read,
+    Write,
+    BufReader,
+    BufWriter
+};
+use std::path::Path;
+
+const CHECKPOINT_MAGIC: &[u8; 8] = b"CNOLFILM";
+const CHECKPOINT_VERSION: u32 = 2;
+
+/// The size of the magic, version, width, height, seed and sample count
+const CHECKPOINT_HEADER_SIZE: u64 = 32;
+
+/// The size of the color, alpha and sample count of a pixel
+const CHECKPOINT_PIXEL_SIZE: u64 = 36;
+
+/// The accumulated samples of every pixel in a render
+#[derive(Debug, Clone)]
+pub struct Film {
+    width: u32,
+    height: u32,
+
+    /// The seed of the sampler that produced the samples
+    seed: u64,
+
+    /// The number of samples every pixel should receive
+    samples: u32,
+
+    pixels: Vec<FilmPixel>
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct FilmPixel {
+    /// The sum of the colors of all samples, each multiplied by its alpha
+    pub sum: Color,
+    pub alpha: f64,
+    pub samples: u32
+}
+
+/// The light arriving at the camera along one ray
+#[derive(Debug, Copy, Clone)]
+pub struct Sample {
+    /// The color, multiplied by the alpha
+    pub color: Color,
+
+    /// How much of the image is covered, from 0 for transparent to 1 for opaque
+    pub alpha: f64
+}
+
+
+impl Film {
+    pub fn new(width: u32, height: u32, seed: u64, samples: u32) -> Film {
+        let pixel = FilmPixel {
+            sum: Color::black(),
+            alpha: 0.0,
+            samples: 0,
+        };
+
+        Film {
+            width,
+            height,
+            seed,
+            samples,
+            pixels: vec![pixel; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples;
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> FilmPixel {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: FilmPixel) {
+        let index = self.index(x, y);
+        self.pixels[index] = pixel;
+    }
+
+    /// Replace the pixels of a tile, given in the same order as `Tile::pixels`
+    pub(crate) fn set_tile(&mut self, tile: &Tile, pixels: &[FilmPixel]) {
+        for ((x, y), &pixel) in tile.pixels().zip(pixels) {
+            self.set_pixel(x, y, pixel);
+        }
+    }
+
+    /// Average the samples of every pixel
+    pub fn to_image(&self) -> DynamicImage {
+        let mut image = DynamicImage::new_rgba8(self.width, self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pixel = self.pixel(x, y);
+                let alpha = pixel.alpha();
+
+                // Images store colors that are not multiplied by their alpha
+                let color = if alpha > 0.0 {
+                    pixel.color().apply_brightness(1.0 / alpha)
+                } else {
+                    Color::black()
+                };
+
+                image.put_pixel(x, y, color.to_rgba(alpha));
+            }
+        }
+
+        image
+    }
+
+    /// Write the film to a file, from which the render can later be resumed.
+    ///
+    /// The film is first written to a temporary file which then replaces the old checkpoint,
+    /// so that a crash while saving does not destroy the previous checkpoint.
+    pub fn save_checkpoint<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let temporary = path.with_extension("tmp");
+
+        {
+            let mut writer = BufWriter::new(File::create(&temporary)?);
+
+            writer.write_all(CHECKPOINT_MAGIC)?;
+            write_u32(&mut writer, CHECKPOINT_VERSION)?;
+            write_u32(&mut writer, self.width)?;
+            write_u32(&mut writer, self.height)?;
+            write_u64(&mut writer, self.seed)?;
+            write_u32(&mut writer, self.samples)?;
+
+            for pixel in self.pixels.iter() {
+                write_u64(&mut writer, pixel.sum.r.to_bits())?;
+                write_u64(&mut writer, pixel.sum.g.to_bits())?;
+                write_u64(&mut writer, pixel.sum.b.to_bits())?;
+                write_u64(&mut writer, pixel.alpha.to_bits())?;
+                write_u32(&mut writer, pixel.samples)?;
+            }
+
+            writer.flush()?;
+        }
+
+        fs::rename(temporary, path)
+    }
+
+    pub fn load_checkpoint<P: AsRef<Path>>(path: P) -> io::Result<Film> {
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(invalid_data("not a checkpoint file"));
+        }
+
+        if read_u32(&mut reader)? != CHECKPOINT_VERSION {
+            return Err(invalid_data("unsupported checkpoint version"));
+        }
+
+        let width = read_u32(&mut reader)?;
+        let height = read_u32(&mut reader)?;
+        let seed = read_u64(&mut reader)?;
+        let samples = read_u32(&mut reader)?;
+
+        // Check the size before allocating the pixels, so that a corrupt file can not ask
+        // for a huge film
+        let pixel_count = width.checked_mul(height)
+            .ok_or_else(|| invalid_data("the checkpoint is too large"))?;
+
+        if CHECKPOINT_HEADER_SIZE + u64::from(pixel_count) * CHECKPOINT_PIXEL_SIZE != length {
+            return Err(invalid_data("the checkpoint size does not match its resolution"));
+        }
+
+        let mut film = Film::new(width, height, seed, samples);
+
+        for pixel in film.pixels.iter_mut() {
+            let r = f64::from_bits(read_u64(&mut reader)?);
+            let g = f64::from_bits(read_u64(&mut reader)?);
+            let b = f64::from_bits(read_u64(&mut reader)?);
+            let alpha = f64::from_bits(read_u64(&mut reader)?);
+            let samples = read_u32(&mut reader)?;
+
+            *pixel = FilmPixel {
+                sum: Color::new(r, g, b),
+                alpha,
+                samples,
+            };
+        }
+
+        Ok(film)
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.width + x) as usize
+    }
+}
+
+
+impl FilmPixel {
+    pub fn add_sample(&mut self, sample: Sample) {
+        self.sum = self.sum + sample.color;
+        self.alpha += sample.alpha;
+        self.samples += 1;
+    }
+
+    /// The average color of all samples, multiplied by the average alpha
+    pub fn color(&self) -> Color {
+        if self.samples == 0 {
+            Color::black()
+        } else {
+            self.sum.apply_brightness(1.0 / f64::from(self.samples))
+        }
+    }
+
+    /// The average alpha of all samples
+    pub fn alpha(&self) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.alpha / f64::from(self.samples)
+        }
+    }
+}
+
+
+impl Sample {
+    pub fn opaque(color: Color) -> Sample {
+        Sample {
+            color,
+            alpha: 1.0,
+        }
+    }
+
+    pub fn transparent() -> Sample {
+        Sample {
+            color: Color::black(),
+            alpha: 0.0,
+        }
+    }
+}
+
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::path::PathBuf;
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("cnol-film-{}-{}.checkpoint", name, std::process::id()))
+    }
+
+    #[test]
+    fn checkpoint_round_trip_keeps_every_pixel() {
+        let mut film = Film::new(3, 2, 42, 16);
+
+        for y in 0..2 {
+            for x in 0..3 {
+                film.set_pixel(x, y, FilmPixel {
+                    sum: Color::new(0.1 * f64::from(x), 0.2 * f64::from(y), 0.3),
+                    alpha: 0.5 * f64::from(x + y),
+                    samples: x + 2 * y,
+                });
+            }
+        }
+
+        let path = checkpoint_path("round-trip");
+        film.save_checkpoint(&path).unwrap();
+        let loaded = Film::load_checkpoint(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((loaded.width(), loaded.height()), (3, 2));
+        assert_eq!(loaded.seed(), 42);
+        assert_eq!(loaded.samples(), 16);
+
+        for y in 0..2 {
+            for x in 0..3 {
+                let (expected, actual) = (film.pixel(x, y), loaded.pixel(x, y));
+
+                assert_eq!(actual.sum.r.to_bits(), expected.sum.r.to_bits());
+                assert_eq!(actual.sum.g.to_bits(), expected.sum.g.to_bits());
+                assert_eq!(actual.sum.b.to_bits(), expected.sum.b.to_bits());
+                assert_eq!(actual.alpha.to_bits(), expected.alpha.to_bits());
+                assert_eq!(actual.samples, expected.samples);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_checkpoint_is_invalid() {
+        let path = checkpoint_path("truncated");
+        Film::new(4, 4, 0, 1).save_checkpoint(&path).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+        let error = Film::load_checkpoint(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn checkpoint_of_another_version_is_invalid() {
+        let path = checkpoint_path("version");
+        Film::new(2, 2, 0, 1).save_checkpoint(&path).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[8..12].copy_from_slice(&1u32.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+
+        let error = Film::load_checkpoint(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn checkpoint_with_huge_resolution_is_invalid() {
+        let path = checkpoint_path("huge");
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(CHECKPOINT_MAGIC);
+        bytes.extend_from_slice(&CHECKPOINT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+
+        let error = Film::load_checkpoint(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
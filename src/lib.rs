@@ -0,0 +1,37 @@
+extern crate image;
+extern crate rand;
+
+pub mod vector;
+pub mod color;
+pub mod ray;
+pub mod film;
+mod microfacet;
+mod sampler;
+mod tile;
+
+pub mod scene;
+pub mod material;
+pub mod texture;
+pub mod shape;
+pub mod light;
+
+pub mod background;
+pub mod camera;
+mod render;
+
+pub use vector::Vector3;
+pub use color::Color;
+pub use scene::Scene;
+pub use material::Material;
+pub use texture::Texture;
+pub use shape::Shape;
+pub use light::Light;
+pub use background::Background;
+pub use camera::Camera;
+pub use film::Film;
+pub use tile::TileOrder;
+pub use render::{
+    render,
+    RenderSettings,
+    RenderOutput
+};
@@ -0,0 +1,380 @@
+use vector::Vector3;
+use color::Color;
+use ray::Ray;
+use sampler;
+
+use std::f64::consts::PI;
+
+use super::{
+    Light,
+    LightSample,
+    Power
+};
+
+/// A glowing parallelogram spanned by two edges from a corner. It only emits light from the
+/// side its normal, `cross(edge_a, edge_b)`, points towards.
+#[derive(Debug)]
+pub struct RectangleLight {
+    pub corner: Vector3,
+    pub edge_a: Vector3,
+    pub edge_b: Vector3,
+    pub color: Color,
+    pub power: Power
+}
+
+/// A glowing disk that only emits light from the side its normal points towards
+#[derive(Debug)]
+pub struct DiskLight {
+    pub center: Vector3,
+    pub normal: Vector3,
+    pub radius: f64,
+    pub color: Color,
+    pub power: Power
+}
+
+/// A glowing sphere
+#[derive(Debug)]
+pub struct SphereLight {
+    pub center: Vector3,
+    pub radius: f64,
+    pub color: Color,
+    pub power: Power
+}
+
+
+impl RectangleLight {
+    /// The light leaving each point of the surface
+    pub fn radiance(&self) -> Color {
+        self.color.apply_brightness(self.power.surface_scale(self.area()))
+    }
+
+    pub fn normal(&self) -> Vector3 {
+        Vector3::cross(self.edge_a, self.edge_b).normal()
+    }
+
+    pub fn area(&self) -> f64 {
+        Vector3::cross(self.edge_a, self.edge_b).length()
+    }
+
+    /// Get the distance along the ray to the light
+    fn hit_distance(&self, ray: &Ray) -> Option<f64> {
+        let distance = plane_distance(ray, self.corner, self.normal())?;
+
+        // Solve `offset = a * edge_a + b * edge_b`, which works for edges that are not
+        // perpendicular
+        let offset = ray.origin + distance * ray.direction - self.corner;
+
+        let aa = Vector3::dot(self.edge_a, self.edge_a);
+        let ab = Vector3::dot(self.edge_a, self.edge_b);
+        let bb = Vector3::dot(self.edge_b, self.edge_b);
+        let determinant = aa * bb - ab * ab;
+
+        if determinant <= 0.0 {
+            return None;
+        }
+
+        let offset_a = Vector3::dot(offset, self.edge_a);
+        let offset_b = Vector3::dot(offset, self.edge_b);
+
+        let a = (bb * offset_a - ab * offset_b) / determinant;
+        let b = (aa * offset_b - ab * offset_a) / determinant;
+
+        if (0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b) {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+impl Light for RectangleLight {
+    fn sample(&self, point: Vector3) -> Option<LightSample> {
+        let a = sampler::gen_range(0.0, 1.0);
+        let b = sampler::gen_range(0.0, 1.0);
+        let light_point = self.corner + a * self.edge_a + b * self.edge_b;
+
+        sample_surface(point, light_point, self.normal(), self.area(), self.radiance())
+    }
+
+    fn power(&self) -> f64 {
+        PI * self.area() * average(self.radiance())
+    }
+
+    fn pdf(&self, point: Vector3, direction: Vector3) -> f64 {
+        let ray = Ray { origin: point, direction };
+
+        match self.hit_distance(&ray) {
+            Some(distance) => surface_pdf(direction, distance, self.normal(), self.area()),
+            None => 0.0,
+        }
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Color)> {
+        self.hit_distance(ray)
+            .map(|distance| (distance, emission(ray, self.normal(), self.radiance())))
+    }
+}
+
+
+impl DiskLight {
+    /// The light leaving each point of the surface
+    pub fn radiance(&self) -> Color {
+        self.color.apply_brightness(self.power.surface_scale(self.area()))
+    }
+
+    pub fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    /// Get the distance along the ray to the light
+    fn hit_distance(&self, ray: &Ray) -> Option<f64> {
+        let distance = plane_distance(ray, self.center, self.normal.normal())?;
+        let point = ray.origin + distance * ray.direction;
+
+        if Vector3::distance(point, self.center) <= self.radius {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+impl Light for DiskLight {
+    fn sample(&self, point: Vector3) -> Option<LightSample> {
+        let normal = self.normal.normal();
+        let (tangent, bitangent) = normal.perpendicular_axes();
+
+        let (x, y) = sampler::disk(self.radius);
+        let light_point = self.center + x * tangent + y * bitangent;
+
+        sample_surface(point, light_point, normal, self.area(), self.radiance())
+    }
+
+    fn power(&self) -> f64 {
+        PI * self.area() * average(self.radiance())
+    }
+
+    fn pdf(&self, point: Vector3, direction: Vector3) -> f64 {
+        let ray = Ray { origin: point, direction };
+
+        match self.hit_distance(&ray) {
+            Some(distance) => surface_pdf(direction, distance, self.normal.normal(), self.area()),
+            None => 0.0,
+        }
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Color)> {
+        self.hit_distance(ray)
+            .map(|distance| (distance, emission(ray, self.normal.normal(), self.radiance())))
+    }
+}
+
+
+impl SphereLight {
+    /// The light leaving each point of the surface
+    pub fn radiance(&self) -> Color {
+        self.color.apply_brightness(self.power.surface_scale(self.area()))
+    }
+
+    pub fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    /// The cosine of the largest angle between the center of the sphere and its outline, as
+    /// seen from a point outside the sphere
+    fn max_cos(&self, point: Vector3) -> Option<f64> {
+        let distance = Vector3::distance(point, self.center);
+
+        if distance <= self.radius {
+            None
+        } else {
+            let sin = self.radius / distance;
+            Some((1.0 - sin * sin).sqrt())
+        }
+    }
+
+    /// Get the distance along the ray to the near side of the light
+    fn hit_distance(&self, ray: &Ray) -> Option<f64> {
+        let projection = Vector3::dot(self.center - ray.origin, ray.direction);
+        let closest_point_on_ray = ray.origin + projection * ray.direction;
+        let distance = Vector3::distance(closest_point_on_ray, self.center);
+
+        if distance > self.radius {
+            return None;
+        }
+
+        let distance_to_entry = projection - (self.radius * self.radius - distance * distance).sqrt();
+
+        if distance_to_entry > 0.0 {
+            Some(distance_to_entry)
+        } else {
+            None
+        }
+    }
+}
+
+impl Light for SphereLight {
+    /// Only the part of the sphere facing the point is sampled, by choosing a direction within
+    /// the cone that the sphere covers
+    fn sample(&self, point: Vector3) -> Option<LightSample> {
+        let max_cos = self.max_cos(point)?;
+
+        let direction = sampler::cone(self.center - point, max_cos);
+        let ray = Ray { origin: point, direction };
+
+        // Rays along the outline of the sphere may miss it due to rounding errors
+        let distance = self.hit_distance(&ray)
+            .unwrap_or_else(|| Vector3::dot(self.center - point, direction));
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.radiance(),
+            pdf: 1.0 / (2.0 * PI * (1.0 - max_cos)),
+        })
+    }
+
+    fn power(&self) -> f64 {
+        PI * self.area() * average(self.radiance())
+    }
+
+    fn pdf(&self, point: Vector3, direction: Vector3) -> f64 {
+        match self.max_cos(point) {
+            Some(max_cos) => {
+                let axis = (self.center - point).normal();
+
+                if Vector3::dot(axis, direction) >= max_cos {
+                    1.0 / (2.0 * PI * (1.0 - max_cos))
+                } else {
+                    0.0
+                }
+            },
+            None => 0.0,
+        }
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Color)> {
+        self.hit_distance(ray).map(|distance| (distance, self.radiance()))
+    }
+}
+
+
+/// Convert a point chosen uniformly over the area of a light into a sample, whose probability
+/// density is measured over the solid angle the light covers
+pub(super) fn sample_surface(
+    point: Vector3,
+    light_point: Vector3,
+    normal: Vector3,
+    area: f64,
+    color: Color
+) -> Option<LightSample> {
+    let delta = light_point - point;
+    let distance = delta.length();
+    let direction = delta / distance;
+
+    let pdf = surface_pdf(direction, distance, normal, area);
+
+    if pdf > 0.0 {
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: color,
+            pdf,
+        })
+    } else {
+        None
+    }
+}
+
+/// The probability density, per solid angle, of sampling a direction towards a point on a
+/// light whose area is sampled uniformly
+pub(super) fn surface_pdf(direction: Vector3, distance: f64, normal: Vector3, area: f64) -> f64 {
+    let cos_light = -Vector3::dot(direction, normal);
+
+    if cos_light > 0.0 {
+        distance * distance / (cos_light * area)
+    } else {
+        0.0
+    }
+}
+
+/// Get the distance along a ray to where it crosses a plane, from either side
+fn plane_distance(ray: &Ray, origin: Vector3, normal: Vector3) -> Option<f64> {
+    let direction = Vector3::dot(ray.direction, normal);
+
+    if direction == 0.0 {
+        return None;
+    }
+
+    let distance = Vector3::dot(origin - ray.origin, normal) / direction;
+
+    if distance > 0.0 {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/// The light leaving a one-sided surface towards the origin of a ray
+fn emission(ray: &Ray, normal: Vector3, color: Color) -> Color {
+    if Vector3::dot(ray.direction, normal) < 0.0 {
+        color
+    } else {
+        Color::black()
+    }
+}
+
+pub(super) fn average(color: Color) -> f64 {
+    (color.r + color.g + color.b) / 3.0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skewed_rectangle_light_is_hit_where_it_is_sampled() {
+        sampler::seed_sample(0, 0, 0, 0);
+
+        let light = RectangleLight {
+            corner: Vector3::new(-0.5, 2.0, -0.5),
+            edge_a: Vector3::new(1.0, 0.0, 0.0),
+            edge_b: Vector3::new(0.6, 0.0, 1.0),
+            color: Color::white(),
+            power: Power::Strength(1.0),
+        };
+        let point = Vector3::new(0.3, 0.0, 0.2);
+
+        for _ in 0..1000 {
+            let sample = light.sample(point).unwrap();
+            let ray = Ray { origin: point, direction: sample.direction };
+
+            let (distance, _) = light.intersect(&ray).expect("the sampled point is on the light");
+            assert!((distance - sample.distance).abs() < 1.0e-9);
+
+            let pdf = light.pdf(point, sample.direction);
+            assert!((pdf - sample.pdf).abs() < 1.0e-9 * sample.pdf);
+        }
+    }
+
+    #[test]
+    fn skewed_rectangle_light_is_not_hit_outside_its_edges() {
+        let light = RectangleLight {
+            corner: Vector3::new(0.0, 1.0, 0.0),
+            edge_a: Vector3::new(1.0, 0.0, 0.0),
+            edge_b: Vector3::new(1.0, 0.0, 1.0),
+            color: Color::white(),
+            power: Power::Strength(1.0),
+        };
+
+        // Inside the unit square the edges would span if they were perpendicular, but outside
+        // the parallelogram
+        let ray = Ray {
+            origin: Vector3::new(0.1, 0.0, 0.9),
+            direction: Vector3::new(0.0, 1.0, 0.0),
+        };
+
+        assert!(light.intersect(&ray).is_none());
+    }
+}
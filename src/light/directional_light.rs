@@ -0,0 +1,64 @@
+use vector::Vector3;
+use color::Color;
+use sampler;
+
+use super::{
+    Light,
+    LightSample,
+    Power
+};
+
+/// A light infinitely far away, such as the sun, whose rays are all parallel
+#[derive(Debug)]
+pub struct DirectionalLight {
+    /// The direction the light travels in
+    pub direction: Vector3,
+    pub color: Color,
+    pub power: Power,
+
+    /// The angle covered by the light as seen from the scene, in degrees. Larger angles give
+    /// softer shadows.
+    pub angular_diameter: f64
+}
+
+
+impl DirectionalLight {
+    /// The light falling on a surface facing the light
+    pub fn irradiance(&self) -> Color {
+        self.color.apply_brightness(self.power.scale(1.0, 1.0))
+    }
+
+    /// Get a random direction towards the light
+    pub fn sample_direction(&self) -> Vector3 {
+        let axis = -self.direction.normal();
+
+        if self.angular_diameter <= 0.0 {
+            return axis;
+        }
+
+        let max_cos = (self.angular_diameter.to_radians() / 2.0).cos();
+        sampler::cone(axis, max_cos)
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: Vector3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: self.sample_direction(),
+            distance: f64::INFINITY,
+            radiance: self.irradiance(),
+            pdf: 1.0,
+        })
+    }
+
+    /// The power falling on a unit area facing the light
+    fn power(&self) -> f64 {
+        let irradiance = self.irradiance();
+        (irradiance.r + irradiance.g + irradiance.b) / 3.0
+    }
+
+    /// The light is not visible, so it can not be hit by chance
+    fn pdf(&self, _point: Vector3, _direction: Vector3) -> f64 {
+        0.0
+    }
+}
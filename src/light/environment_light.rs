@@ -0,0 +1,392 @@
+use image::{
+    self,
+    hdr::HDRDecoder
+};
+
+use vector::Vector3;
+use color::Color;
+use ray::Ray;
+use sampler;
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{
+    Light,
+    LightSample
+};
+
+/// An image covering every direction, in equirectangular projection: the columns go around
+/// the y-axis and the rows go from straight up to straight down.
+#[derive(Clone)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Arc<Vec<Color>>
+}
+
+/// Light arriving from infinitely far away in every direction, such as from the sky.
+///
+/// Directions are sampled in proportion to their brightness, so that small and bright areas
+/// of the map, such as the sun, do not cause noise.
+#[derive(Debug)]
+pub struct EnvironmentLight {
+    map: EnvironmentMap,
+    strength: f64,
+    distribution: Distribution2D
+}
+
+
+impl EnvironmentMap {
+    /// Create a map from its pixels, given row by row
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> EnvironmentMap {
+        assert_eq!(pixels.len(), width * height, "the size of the map does not match its pixels");
+
+        EnvironmentMap {
+            width,
+            height,
+            pixels: Arc::new(pixels),
+        }
+    }
+
+    /// Create a map by evaluating the color in the direction of every pixel
+    pub fn from_fn<F>(width: usize, height: usize, color: F) -> EnvironmentMap
+        where F: Fn(Vector3) -> Color {
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as f64 + 0.5) / width as f64;
+                let v = (y as f64 + 0.5) / height as f64;
+
+                pixels.push(color(direction_from_coordinates(u, v)));
+            }
+        }
+
+        EnvironmentMap::new(width, height, pixels)
+    }
+
+    /// Load a Radiance HDR image
+    pub fn open_hdr<P: AsRef<Path>>(path: P) -> image::ImageResult<EnvironmentMap> {
+        let file = File::open(path)?;
+        let decoder = HDRDecoder::new(BufReader::new(file))?;
+
+        let metadata = decoder.metadata();
+        let pixels = decoder.read_image_hdr()?
+            .into_iter()
+            .map(|pixel| Color::new(
+                f64::from(pixel.data[0]),
+                f64::from(pixel.data[1]),
+                f64::from(pixel.data[2])
+            ))
+            .collect();
+
+        Ok(EnvironmentMap::new(metadata.width as usize, metadata.height as usize, pixels))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the color in a direction
+    pub fn color(&self, direction: Vector3) -> Color {
+        let (u, v) = coordinates_from_direction(direction);
+        let (x, y) = self.pixel_at(u, v);
+
+        self.pixel(x, y)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    fn pixel_at(&self, u: f64, v: f64) -> (usize, usize) {
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        (x, y)
+    }
+}
+
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EnvironmentMap({}x{})", self.width, self.height)
+    }
+}
+
+
+impl EnvironmentLight {
+    pub fn new(map: EnvironmentMap, strength: f64) -> EnvironmentLight {
+        let mut weights = Vec::with_capacity(map.width * map.height);
+
+        for y in 0..map.height {
+            // Rows near the poles cover a smaller part of the sphere
+            let sin_theta = (PI * (y as f64 + 0.5) / map.height as f64).sin();
+
+            for x in 0..map.width {
+                weights.push(map.pixel(x, y).luminance() * sin_theta);
+            }
+        }
+
+        EnvironmentLight {
+            distribution: Distribution2D::new(map.width, map.height, &weights),
+            map,
+            strength,
+        }
+    }
+
+    pub fn open_hdr<P: AsRef<Path>>(path: P, strength: f64) -> image::ImageResult<EnvironmentLight> {
+        Ok(EnvironmentLight::new(EnvironmentMap::open_hdr(path)?, strength))
+    }
+
+    /// Get the light arriving from a direction
+    pub fn radiance(&self, direction: Vector3) -> Color {
+        self.map.color(direction).apply_brightness(self.strength)
+    }
+
+    /// Convert a probability density over the map to one over the sphere of directions
+    fn direction_pdf(&self, u: f64, v: f64) -> f64 {
+        let sin_theta = (PI * v).sin();
+
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let (x, y) = self.map.pixel_at(u, v);
+        self.distribution.pdf(x, y) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn sample(&self, _point: Vector3) -> Option<LightSample> {
+        let (u, v) = self.distribution.sample()?;
+
+        let pdf = self.direction_pdf(u, v);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        let direction = direction_from_coordinates(u, v);
+
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            radiance: self.radiance(direction),
+            pdf,
+        })
+    }
+
+    /// The power falling on a unit area, from every direction
+    fn power(&self) -> f64 {
+        let total = self.map.pixels.iter().map(|color| color.luminance()).sum::<f64>();
+        PI * self.strength * total / self.map.pixels.len() as f64
+    }
+
+    fn pdf(&self, _point: Vector3, direction: Vector3) -> f64 {
+        let (u, v) = coordinates_from_direction(direction);
+        self.direction_pdf(u, v)
+    }
+
+    /// Every ray that escapes the scene hits the environment
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Color)> {
+        Some((f64::INFINITY, self.radiance(ray.direction)))
+    }
+}
+
+
+/// Chooses cells of a grid with probability proportional to their weights, by first choosing
+/// a row and then a column within that row.
+// http://www.pbr-book.org/3ed-2018/Monte_Carlo_Integration/2D_Sampling_with_Multidimensional_Transformations.html
+#[derive(Debug)]
+struct Distribution2D {
+    width: usize,
+    height: usize,
+
+    weights: Vec<f64>,
+    average: f64,
+
+    /// The cumulative distribution of the columns within every row
+    rows: Vec<Vec<f64>>,
+
+    /// The cumulative distribution of the rows
+    marginal: Vec<f64>
+}
+
+impl Distribution2D {
+    /// Create a distribution from the weights of the cells, given row by row. Weights that are
+    /// negative or not finite, as in some HDR images, are never chosen.
+    fn new(width: usize, height: usize, weights: &[f64]) -> Distribution2D {
+        let weights = weights.iter()
+            .map(|&weight| if weight.is_finite() && weight > 0.0 { weight } else { 0.0 })
+            .collect::<Vec<_>>();
+
+        let rows = weights.chunks(width)
+            .map(cumulative)
+            .collect::<Vec<_>>();
+
+        let row_totals = weights.chunks(width)
+            .map(|row| row.iter().sum::<f64>())
+            .collect::<Vec<_>>();
+
+        let total = row_totals.iter().sum::<f64>();
+
+        Distribution2D {
+            width,
+            height,
+            average: total / weights.len() as f64,
+            weights,
+            rows,
+            marginal: cumulative(&row_totals),
+        }
+    }
+
+    /// Get a random point in the unit square, with the probability density given by `pdf`
+    fn sample(&self) -> Option<(f64, f64)> {
+        if self.average <= 0.0 {
+            return None;
+        }
+
+        let (y, dy) = sample_cumulative(&self.marginal, sampler::gen_range(0.0, 1.0));
+        let (x, dx) = sample_cumulative(&self.rows[y], sampler::gen_range(0.0, 1.0));
+
+        Some(((x as f64 + dx) / self.width as f64, (y as f64 + dy) / self.height as f64))
+    }
+
+    /// The probability density of sampling a point within a cell, over the unit square
+    fn pdf(&self, x: usize, y: usize) -> f64 {
+        if self.average <= 0.0 {
+            0.0
+        } else {
+            self.weights[y * self.width + x] / self.average
+        }
+    }
+}
+
+/// The normalized running total of the values, starting at 0 and ending at 1
+fn cumulative(values: &[f64]) -> Vec<f64> {
+    let mut sums = Vec::with_capacity(values.len() + 1);
+
+    let mut sum = 0.0;
+    sums.push(sum);
+    for value in values {
+        sum += value;
+        sums.push(sum);
+    }
+
+    if sum > 0.0 {
+        for value in sums.iter_mut() {
+            *value /= sum;
+        }
+    }
+
+    sums
+}
+
+/// Find the interval of a cumulative distribution that contains a value, and how far into
+/// the interval the value is
+fn sample_cumulative(cumulative: &[f64], value: f64) -> (usize, f64) {
+    let intervals = cumulative.len() - 1;
+
+    let index = match cumulative.binary_search_by(|a| a.partial_cmp(&value).unwrap()) {
+        Ok(index) => index,
+        Err(index) => index - 1,
+    }.min(intervals - 1);
+
+    let start = cumulative[index];
+    let width = cumulative[index + 1] - start;
+
+    let offset = if width > 0.0 { (value - start) / width } else { 0.5 };
+
+    (index, offset.clamp(0.0, 1.0))
+}
+
+
+fn direction_from_coordinates(u: f64, v: f64) -> Vector3 {
+    let phi = 2.0 * PI * u;
+    let theta = PI * v;
+
+    Vector3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+}
+
+fn coordinates_from_direction(direction: Vector3) -> (f64, f64) {
+    let direction = direction.normal();
+
+    let phi = direction.z.atan2(direction.x);
+    let theta = direction.y.clamp(-1.0, 1.0).acos();
+
+    let u = if phi < 0.0 { phi + 2.0 * PI } else { phi } / (2.0 * PI);
+    let v = theta / PI;
+
+    (u, v)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_light() -> EnvironmentLight {
+        let map = EnvironmentMap::from_fn(16, 8, |direction| {
+            // A bright spot over a dim gradient
+            let spot = Vector3::dot(direction, Vector3::new(0.6, 0.8, 0.0)).max(0.0).powi(20);
+            Color::new(0.1, 0.1, 0.1).apply_brightness(1.0 + direction.y) +
+                Color::new(5.0, 4.0, 3.0).apply_brightness(spot)
+        });
+
+        EnvironmentLight::new(map, 1.0)
+    }
+
+    #[test]
+    fn sampled_directions_have_the_pdf_of_their_direction() {
+        sampler::seed_sample(0, 0, 0, 0);
+        let light = test_light();
+        let point = Vector3::zero();
+
+        for _ in 0..1000 {
+            let sample = light.sample(point).unwrap();
+            let pdf = light.pdf(point, sample.direction);
+
+            assert!(sample.pdf > 0.0);
+            assert!((pdf - sample.pdf).abs() <= 1.0e-6 * sample.pdf, "{} != {}", pdf, sample.pdf);
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one_over_the_sphere() {
+        sampler::seed_sample(0, 0, 0, 1);
+        let light = test_light();
+
+        let count = 200_000;
+        let total = (0..count)
+            .map(|_| light.pdf(Vector3::zero(), sampler::sphere()))
+            .sum::<f64>();
+
+        let integral = 4.0 * PI * total / count as f64;
+        assert!((integral - 1.0).abs() < 0.02, "the pdf integrates to {}", integral);
+    }
+
+    #[test]
+    fn non_finite_texels_are_never_sampled() {
+        sampler::seed_sample(0, 0, 0, 2);
+
+        let mut pixels = vec![Color::new(1.0, 1.0, 1.0); 4 * 2];
+        pixels[1] = Color::new(f64::NAN, 0.0, 0.0);
+        pixels[6] = Color::new(f64::INFINITY, 1.0, 1.0);
+
+        let light = EnvironmentLight::new(EnvironmentMap::new(4, 2, pixels), 1.0);
+
+        for _ in 0..1000 {
+            let sample = light.sample(Vector3::zero()).unwrap();
+            assert!(sample.pdf.is_finite() && sample.pdf > 0.0);
+            assert!(sample.radiance.r.is_finite());
+        }
+    }
+}
@@ -1,33 +1,70 @@
-
 mod point_light;
+mod directional_light;
+mod spot_light;
+mod area_light;
+mod shape_light;
+mod environment_light;
+mod sky;
+mod power;
 
 pub use self::point_light::PointLight;
+pub use self::directional_light::DirectionalLight;
+pub use self::spot_light::{
+    SpotLight,
+    Gobo
+};
+pub use self::area_light::{
+    RectangleLight,
+    DiskLight,
+    SphereLight
+};
+pub use self::shape_light::ShapeLight;
+pub use self::environment_light::{
+    EnvironmentLight,
+    EnvironmentMap
+};
+pub use self::sky::Sky;
+pub use self::power::{
+    Power,
+    Falloff
+};
 use vector::Vector3;
 use color::Color;
+use ray::Ray;
+
+use std::fmt::Debug;
+
+/// Light arriving at a point from a randomly chosen point on a light
+#[derive(Debug, Clone)]
+pub struct LightSample {
+    /// The direction from the illuminated point towards the light
+    pub direction: Vector3,
+
+    /// The distance to the sampled point on the light
+    pub distance: f64,
 
-#[derive(Debug)]
-pub enum Light {
-    Point(PointLight)
+    /// The light arriving at the illuminated point
+    pub radiance: Color,
+
+    /// The probability density of choosing this direction, or 1 if the light could only have
+    /// been sampled in this direction
+    pub pdf: f64
 }
 
-impl Light {
-    /// Get a random point within the light
-    pub fn sample_point(&self) -> Vector3 {
-        match self {
-            Light::Point(point_light) => point_light.sample_point()
-        }
-    }
+pub trait Light: Debug + Send + Sync {
+    /// Sample the light arriving at a point, if any
+    fn sample(&self, point: Vector3) -> Option<LightSample>;
 
-    /// Get the color of the light
-    pub fn color(&self) -> Color {
-        match self {
-            Light::Point(point_light) => point_light.color
-        }
-    }
+    /// The total power emitted by the light, used to choose which light to sample. Lights that
+    /// are infinitely far away give the power falling on a unit area facing them.
+    fn power(&self) -> f64;
 
+    /// The probability density of `sample` returning the given direction from a point
+    fn pdf(&self, point: Vector3, direction: Vector3) -> f64;
 
-    /// Get the brightness of this light
-    pub fn brightness(&self, distance: f64) -> f64 {
-        1.2 / (distance * distance)
+    /// Find where a ray hits the visible surface of the light, if it has one. Returns the
+    /// distance along the ray and the light leaving the surface towards the origin of the ray.
+    fn intersect(&self, _ray: &Ray) -> Option<(f64, Color)> {
+        None
     }
-}
\ No newline at end of file
+}
@@ -1,32 +1,80 @@
-use rand;
-
-use rand::Rng;
-
 use vector::Vector3;
 use color::Color;
+use sampler;
+
+use std::f64::consts::PI;
+
+use super::{
+    Light,
+    LightSample,
+    Power,
+    Falloff
+};
+use super::area_light::average;
 
 #[derive(Debug)]
 pub struct PointLight {
     pub point: Vector3,
     pub color: Color,
-    pub size: f64
+    pub size: f64,
+
+    pub power: Power,
+    pub falloff: Falloff
 }
 
 
 impl PointLight {
     pub fn sample_point(&self) -> Vector3 {
-        let mut rng = rand::thread_rng();
-
-        let offset = if self.size > 0.0 {
-             Vector3::new(
-                rng.gen_range(-self.size, self.size),
-                rng.gen_range(-self.size, self.size),
-                rng.gen_range(-self.size, self.size)
-            )
-        } else {
-            Vector3::zero()
-        };
-
-        self.point + offset
+        sample_point(self.point, self.size)
+    }
+
+    /// The light shining in every direction, before it falls off with distance
+    pub fn intensity(&self) -> Color {
+        intensity(self.color, self.power)
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: Vector3) -> Option<LightSample> {
+        let delta = self.sample_point() - point;
+        let distance = delta.length();
+
+        Some(LightSample {
+            direction: delta / distance,
+            distance,
+            radiance: self.intensity().apply_brightness(self.falloff.attenuation(distance)),
+            pdf: 1.0,
+        })
     }
-}
\ No newline at end of file
+
+    fn power(&self) -> f64 {
+        4.0 * PI * average(self.intensity())
+    }
+
+    /// A point can not be hit by chance
+    fn pdf(&self, _point: Vector3, _direction: Vector3) -> f64 {
+        0.0
+    }
+}
+
+
+/// A random point within a cube around the center of a light of the given size, which softens
+/// its shadows
+pub(super) fn sample_point(center: Vector3, size: f64) -> Vector3 {
+    let offset = if size > 0.0 {
+        Vector3::new(
+            sampler::gen_range(-size, size),
+            sampler::gen_range(-size, size),
+            sampler::gen_range(-size, size)
+        )
+    } else {
+        Vector3::zero()
+    };
+
+    center + offset
+}
+
+/// The intensity of a light that shines with the given color and power from a point
+pub(super) fn intensity(color: Color, power: Power) -> Color {
+    color.apply_brightness(power.point_scale())
+}
@@ -0,0 +1,130 @@
+use std::f64::consts::PI;
+
+/// The luminous efficacy of light at the wavelength the eye is most sensitive to
+const LUMENS_PER_WATT: f64 = 683.0;
+
+/// How bright a light is. The light's color tints the power, so for physical units it should
+/// have a brightness of about 1, such as a color from `Color::from_temperature`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Power {
+    /// A multiplier on the color of the light, without units
+    Strength(f64),
+
+    /// The total radiant power, in watts. For directional lights, the power falling on each
+    /// square meter facing the light.
+    Watts(f64),
+
+    /// The total luminous power, in lumens. For directional lights, the illuminance in lux.
+    Lumens(f64),
+
+    /// The luminous intensity, in candela, which is how bright the light looks from a
+    /// distance. For spot lights, the intensity along the direction of the light, and for area
+    /// lights, the intensity facing a flat light. For directional lights, the illuminance in
+    /// lux.
+    Intensity(f64)
+}
+
+/// How the light from a point or spot light weakens with distance. Lights with a surface, such
+/// as area and shape lights, always fall off physically, as their falloff follows from the
+/// size they appear to have.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Falloff {
+    /// Physically correct
+    InverseSquare,
+
+    Linear,
+
+    /// The light has the same brightness at every distance
+    None,
+
+    /// Inverse-square falloff that smoothly reaches zero at the given distance, as in many real
+    /// time renderers. A radius of zero or less gives no light at all.
+    // https://google.github.io/filament/Filament.html#lighting/directlighting/punctuallights
+    Radius(f64)
+}
+
+
+impl Power {
+    /// Convert to a multiplier on the color of a light, given how much of its quantity, such as
+    /// radiance, the light has per watt of power and per watt per steradian of intensity
+    pub fn scale(&self, per_watt: f64, per_intensity: f64) -> f64 {
+        match *self {
+            Power::Strength(strength) => strength,
+            Power::Watts(watts) => watts * per_watt,
+            Power::Lumens(lumens) => lumens / LUMENS_PER_WATT * per_watt,
+            Power::Intensity(candela) => candela / LUMENS_PER_WATT * per_intensity,
+        }
+    }
+
+    /// The multiplier on the color of a light that shines equally in every direction from a
+    /// point, which gives its intensity
+    pub fn point_scale(&self) -> f64 {
+        self.scale(1.0 / (4.0 * PI), 1.0)
+    }
+
+    /// The multiplier on the color of a diffusely emitting surface, which gives its radiance
+    pub fn surface_scale(&self, area: f64) -> f64 {
+        self.scale(1.0 / (PI * area), 1.0 / area)
+    }
+}
+
+
+impl Falloff {
+    /// The fraction of the light's intensity left at a distance
+    pub fn attenuation(&self, distance: f64) -> f64 {
+        match *self {
+            Falloff::InverseSquare => 1.0 / (distance * distance),
+            Falloff::Linear => 1.0 / distance,
+            Falloff::None => 1.0,
+            Falloff::Radius(radius) => {
+                if distance >= radius {
+                    return 0.0;
+                }
+
+                let ratio = distance / radius;
+                let window = (1.0 - ratio.powi(4)).max(0.0);
+
+                window * window / (distance * distance)
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_of_the_same_light_agree() {
+        // A watt of light at the most visible wavelength, shining equally in every direction
+        let watts = Power::Watts(1.0);
+        let lumens = Power::Lumens(LUMENS_PER_WATT);
+        let candela = Power::Intensity(LUMENS_PER_WATT / (4.0 * PI));
+
+        assert!((lumens.point_scale() - watts.point_scale()).abs() < 1.0e-12);
+        assert!((candela.point_scale() - watts.point_scale()).abs() < 1.0e-12);
+
+        // A flat diffuse light is brightest facing it, with an intensity of its power over pi
+        let candela = Power::Intensity(LUMENS_PER_WATT / PI);
+        assert!((candela.surface_scale(2.0) - watts.surface_scale(2.0)).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn radius_falloff_reaches_zero_at_the_radius() {
+        let falloff = Falloff::Radius(2.0);
+
+        assert!(falloff.attenuation(1.0) > 0.0);
+        assert_eq!(falloff.attenuation(2.0), 0.0);
+        assert_eq!(falloff.attenuation(3.0), 0.0);
+    }
+
+    #[test]
+    fn empty_radius_gives_no_light() {
+        for &radius in &[0.0, -1.0] {
+            for &distance in &[0.0, 0.5, 1.0] {
+                assert_eq!(Falloff::Radius(radius).attenuation(distance), 0.0);
+            }
+        }
+    }
+}
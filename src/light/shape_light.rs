@@ -0,0 +1,60 @@
+use vector::Vector3;
+use color::Color;
+use ray::Ray;
+use shape::Shape;
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use super::{
+    Light,
+    LightSample
+};
+use super::area_light::{
+    sample_surface,
+    surface_pdf,
+    average
+};
+
+/// The light given off by the surface of an emissive object.
+///
+/// The object itself is hit by rays like any other object, so the light has no visible
+/// surface of its own.
+#[derive(Debug)]
+pub struct ShapeLight {
+    pub shape: Arc<dyn Shape>,
+    pub color: Color
+}
+
+
+impl Light for ShapeLight {
+    fn sample(&self, point: Vector3) -> Option<LightSample> {
+        let area = self.shape.surface_area()?;
+        let (light_point, normal) = self.shape.sample_surface()?;
+
+        sample_surface(point, light_point, normal, area, self.color)
+    }
+
+    fn power(&self) -> f64 {
+        match self.shape.surface_area() {
+            Some(area) => PI * area * average(self.color),
+            None => 0.0,
+        }
+    }
+
+    fn pdf(&self, point: Vector3, direction: Vector3) -> f64 {
+        let area = match self.shape.surface_area() {
+            Some(area) => area,
+            None => return 0.0,
+        };
+
+        let ray = Ray { origin: point, direction };
+
+        match self.shape.first_intersection(&ray) {
+            Some((entry, _)) if entry.distance > 0.0 => {
+                surface_pdf(direction, entry.distance, entry.normal, area)
+            },
+            _ => 0.0,
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use vector::Vector3;
+use color::Color;
+
+use std::f64::consts::PI;
+
+use super::{
+    EnvironmentLight,
+    EnvironmentMap,
+    DirectionalLight,
+    Power
+};
+
+/// A clear sky lit by the sun, following the model by Preetham, Shirley and Smits.
+///
+/// The sky itself does not contain the sun: pair it with the light returned by `Sky::sun`.
+// https://www2.cs.duke.edu/courses/cps124/spring08/assign/07_papers/p91-preetham.pdf
+#[derive(Debug, Clone)]
+pub struct Sky {
+    /// The angle of the sun above the horizon, in degrees
+    pub sun_elevation: f64,
+
+    /// The angle of the sun around the y-axis, in degrees, going from the z-axis to the x-axis
+    pub sun_azimuth: f64,
+
+    /// The haziness of the air, from 2 for a very clear sky to about 10 for a hazy one
+    pub turbidity: f64,
+
+    /// Scales the brightness of the sky, which is otherwise measured in kcd/m²
+    pub strength: f64
+}
+
+/// The resolution of the environment map the sky is sampled from when used as a light
+const LIGHT_MAP_SIZE: (usize, usize) = (512, 256);
+
+
+impl Sky {
+    /// The direction towards the sun
+    pub fn sun_direction(&self) -> Vector3 {
+        let elevation = self.sun_elevation.to_radians();
+        let azimuth = self.sun_azimuth.to_radians();
+
+        Vector3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos()
+        )
+    }
+
+    /// Get the light arriving from a direction. Below the horizon, the sky keeps the color it
+    /// has at the horizon.
+    pub fn radiance(&self, direction: Vector3) -> Color {
+        let direction = direction.normal();
+        let sun = self.sun_direction();
+
+        let theta_sun = (PI / 2.0 - self.sun_elevation.to_radians()).clamp(0.0, PI / 2.0);
+        let cos_theta = direction.y.max(0.001);
+        let gamma = Vector3::dot(direction, sun).clamp(-1.0, 1.0).acos();
+
+        let t = self.turbidity;
+        let luminance = self.zenith_luminance(theta_sun) *
+            perez_ratio(luminance_coefficients(t), cos_theta, gamma, theta_sun);
+        let x = self.zenith_x(theta_sun) * perez_ratio(x_coefficients(t), cos_theta, gamma, theta_sun);
+        let y = self.zenith_y(theta_sun) * perez_ratio(y_coefficients(t), cos_theta, gamma, theta_sun);
+
+        Color::from_xyy(x, y, luminance * self.strength)
+    }
+
+    /// Render the sky into a map
+    pub fn environment_map(&self, width: usize, height: usize) -> EnvironmentMap {
+        EnvironmentMap::from_fn(width, height, |direction| self.radiance(direction))
+    }
+
+    /// Light the scene with the sky
+    pub fn light(&self) -> EnvironmentLight {
+        let (width, height) = LIGHT_MAP_SIZE;
+        EnvironmentLight::new(self.environment_map(width, height), 1.0)
+    }
+
+    /// The sun that lights the sky, tinted by the air its light passes through
+    pub fn sun(&self, strength: f64) -> DirectionalLight {
+        let transmittance = self.sun_transmittance();
+
+        DirectionalLight {
+            direction: -self.sun_direction(),
+            color: transmittance,
+            power: Power::Strength(strength),
+            angular_diameter: 0.53,
+        }
+    }
+
+    /// The fraction of sunlight that passes through the atmosphere, for red, green and blue
+    /// light respectively
+    fn sun_transmittance(&self) -> Color {
+        let theta = PI / 2.0 - self.sun_elevation.to_radians();
+
+        if theta >= PI / 2.0 {
+            return Color::black();
+        }
+
+        // The relative length of the path through the atmosphere
+        let theta_degrees = theta.to_degrees();
+        let air_mass = 1.0 / (theta.cos() + 0.15 * (93.885 - theta_degrees).powf(-1.253));
+
+        // Rayleigh scattering by the air and Mie scattering by aerosols, at wavelengths in µm
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |wavelength: f64| {
+            let rayleigh = (-0.008735 * wavelength.powf(-4.08) * air_mass).exp();
+            let aerosol = (-beta * wavelength.powf(-1.3) * air_mass).exp();
+
+            rayleigh * aerosol
+        };
+
+        Color::new(transmittance(0.65), transmittance(0.57), transmittance(0.475))
+    }
+
+    fn zenith_luminance(&self, theta_sun: f64) -> f64 {
+        let t = self.turbidity;
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+
+        (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192
+    }
+
+    fn zenith_x(&self, theta_sun: f64) -> f64 {
+        zenith_chromaticity(self.turbidity, theta_sun, [
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ])
+    }
+
+    fn zenith_y(&self, theta_sun: f64) -> f64 {
+        zenith_chromaticity(self.turbidity, theta_sun, [
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ])
+    }
+}
+
+
+type PerezCoefficients = [f64; 5];
+
+fn luminance_coefficients(t: f64) -> PerezCoefficients {
+    [
+        0.1787 * t - 1.4630,
+        -0.3554 * t + 0.4275,
+        -0.0227 * t + 5.3251,
+        0.1206 * t - 2.5771,
+        -0.0670 * t + 0.3703,
+    ]
+}
+
+fn x_coefficients(t: f64) -> PerezCoefficients {
+    [
+        -0.0193 * t - 0.2592,
+        -0.0665 * t + 0.0008,
+        -0.0004 * t + 0.2125,
+        -0.0641 * t - 0.8989,
+        -0.0033 * t + 0.0452,
+    ]
+}
+
+fn y_coefficients(t: f64) -> PerezCoefficients {
+    [
+        -0.0167 * t - 0.2608,
+        -0.0950 * t + 0.0092,
+        -0.0079 * t + 0.2102,
+        -0.0441 * t - 1.6537,
+        -0.0109 * t + 0.0529,
+    ]
+}
+
+/// The Perez sky distribution in a direction, relative to the distribution at the zenith
+fn perez_ratio(coefficients: PerezCoefficients, cos_theta: f64, gamma: f64, theta_sun: f64) -> f64 {
+    let [a, b, c, d, e] = coefficients;
+
+    let perez = |cos_theta: f64, gamma: f64| {
+        (1.0 + a * (b / cos_theta).exp()) *
+            (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+    };
+
+    perez(cos_theta, gamma) / perez(1.0, theta_sun)
+}
+
+/// Evaluate the polynomial `[T², T, 1] * matrix * [θ³, θ², θ, 1]`
+fn zenith_chromaticity(t: f64, theta_sun: f64, matrix: [[f64; 4]; 3]) -> f64 {
+    let turbidity = [t * t, t, 1.0];
+    let theta = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+
+    let mut sum = 0.0;
+    for (row, turbidity) in matrix.iter().zip(turbidity.iter()) {
+        for (value, theta) in row.iter().zip(theta.iter()) {
+            sum += turbidity * value * theta;
+        }
+    }
+
+    sum
+}
@@ -0,0 +1,184 @@
+use image::{
+    self,
+    RgbImage
+};
+
+use vector::Vector3;
+use color::Color;
+use texture::{
+    ImageTexture,
+    Wrap
+};
+
+use std::f64::consts::PI;
+use std::path::Path;
+
+use super::{
+    Light,
+    LightSample,
+    Power,
+    Falloff
+};
+use super::point_light;
+use super::area_light::average;
+
+/// A point light that only shines within a cone
+#[derive(Debug)]
+pub struct SpotLight {
+    pub point: Vector3,
+
+    /// The direction the cone is pointing in
+    pub direction: Vector3,
+
+    pub color: Color,
+    pub size: f64,
+
+    /// The power the light would have if it shone in every direction, so that changing the
+    /// size of the cone does not change its brightness
+    pub power: Power,
+    pub falloff: Falloff,
+
+    /// The angle from the direction within which the light has full brightness, in degrees
+    pub inner_angle: f64,
+
+    /// The angle from the direction outside of which there is no light, in degrees
+    pub outer_angle: f64,
+
+    /// An image projected by the light
+    pub gobo: Option<Gobo>
+}
+
+/// An image placed in front of a spot light, covering its whole cone
+#[derive(Debug, Clone)]
+pub struct Gobo {
+    image: ImageTexture
+}
+
+
+impl SpotLight {
+    pub fn sample_point(&self) -> Vector3 {
+        point_light::sample_point(self.point, self.size)
+    }
+
+    /// The light shining along the direction of the light, before it falls off with distance
+    pub fn intensity(&self) -> Color {
+        point_light::intensity(self.color, self.power)
+    }
+
+    /// The color of the light travelling in a direction from the light
+    pub fn emission(&self, direction: Vector3) -> Color {
+        let axis = self.direction.normal();
+        let cos_angle = Vector3::dot(direction, axis);
+
+        let cos_inner = self.inner_angle.to_radians().cos();
+        let cos_outer = self.outer_angle.to_radians().cos();
+
+        if cos_angle <= cos_outer {
+            return Color::black();
+        }
+
+        let falloff = if cos_angle >= cos_inner {
+            1.0
+        } else {
+            smoothstep((cos_angle - cos_outer) / (cos_inner - cos_outer))
+        };
+
+        let color = self.intensity().apply_brightness(falloff);
+
+        match self.gobo {
+            Some(ref gobo) => {
+                // Project the direction onto a plane in front of the light
+                let (tangent, bitangent) = axis.perpendicular_axes();
+                let scale = 2.0 * self.outer_angle.to_radians().tan() * cos_angle;
+
+                let u = 0.5 + Vector3::dot(direction, tangent) / scale;
+                let v = 0.5 - Vector3::dot(direction, bitangent) / scale;
+
+                color.multiply(gobo.color(u, v))
+            },
+            None => color,
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: Vector3) -> Option<LightSample> {
+        let delta = self.sample_point() - point;
+        let distance = delta.length();
+        let direction = delta / distance;
+
+        let radiance = self.emission(-direction).apply_brightness(self.falloff.attenuation(distance));
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance,
+            pdf: 1.0,
+        })
+    }
+
+    fn power(&self) -> f64 {
+        // The solid angle of the cone, halfway through the falloff
+        let angle = (self.inner_angle + self.outer_angle).to_radians() / 2.0;
+        let solid_angle = 2.0 * PI * (1.0 - angle.cos());
+
+        solid_angle * average(self.intensity())
+    }
+
+    /// A point can not be hit by chance
+    fn pdf(&self, _point: Vector3, _direction: Vector3) -> f64 {
+        0.0
+    }
+}
+
+
+impl Gobo {
+    /// Create a gobo from an image, which must not be empty
+    pub fn new(image: RgbImage) -> Gobo {
+        Gobo::from_texture(ImageTexture::new(image))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> image::ImageResult<Gobo> {
+        Ok(Gobo::from_texture(ImageTexture::open(path)?))
+    }
+
+    fn from_texture(mut image: ImageTexture) -> Gobo {
+        image.wrap = Wrap::Clamp;
+
+        Gobo {
+            image
+        }
+    }
+
+    /// Get the color at a point of the image, where both coordinates are in the range [0, 1]
+    /// and `v` goes from the top of the image to the bottom. Outside of the image, no light
+    /// passes through.
+    pub fn color(&self, u: f64, v: f64) -> Color {
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return Color::black();
+        }
+
+        self.image.sample(u, 1.0 - v)
+    }
+}
+
+
+fn smoothstep(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gobo_covers_the_unit_square() {
+        let gobo = Gobo::new(RgbImage::from_pixel(2, 2, image::Rgb([255, 255, 255])));
+
+        assert!(gobo.color(0.0, 0.0).r > 0.0);
+        assert!(gobo.color(0.999, 0.999).r > 0.0);
+        assert_eq!(gobo.color(1.0, 0.5).r, 0.0);
+        assert_eq!(gobo.color(0.5, -0.1).r, 0.0);
+    }
+}
@@ -1,56 +1,92 @@
 extern crate image;
-extern crate rand;
-
-mod vector;
-mod color;
-mod ray;
-
-mod scene;
-mod material;
-mod shape;
-mod light;
+extern crate cnol_ray_tracer;
 
 use image::{
+    imageops,
     DynamicImage,
-    GenericImage
+    FilterType,
+    ImageBuffer,
+    Rgba
 };
 
-use vector::Vector3;
-use shape::Sphere;
-use scene::Scene;
-use material::Material;
-use color::Color;
-use shape::Shape;
-use ray::Ray;
-
-use shape::Plane;
-use light::Light;
-use light::PointLight;
+use cnol_ray_tracer::{
+    Vector3,
+    Scene,
+    Material,
+    Color,
+    Film,
+    RenderSettings,
+    TileOrder
+};
+use cnol_ray_tracer::shape::{
+    Sphere,
+    Plane,
+    intersection,
+    difference
+};
+use cnol_ray_tracer::light::{
+    PointLight,
+    Power,
+    Falloff
+};
+use cnol_ray_tracer::scene::Integrator;
 
+use std::env;
+use std::process;
+use std::path::PathBuf;
 use std::time;
-use std::thread;
-use std::sync::{
-    Arc,
-    Mutex,
-    MutexGuard,
-    mpsc,
-    mpsc::{
-        Sender,
-        Receiver
+use std::sync::Arc;
+
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    });
+
+    let mut scene = create_scene();
+
+    if options.preview {
+        scene.set_integrator(Integrator::DirectLighting);
     }
-};
 
+    // Render at twice the scale and downsample
+    let mut film = match options.resume {
+        Some(ref path) => Film::load_checkpoint(path).unwrap_or_else(|error| {
+            eprintln!("Failed to load checkpoint {}: {}", path.display(), error);
+            process::exit(1);
+        }),
+        None => {
+            let res = options.resolution.unwrap_or(800);
+            Film::new(res * 2, res * 2, options.seed.unwrap_or(0), options.samples.unwrap_or(1))
+        },
+    };
+
+    if let Some(samples) = options.samples {
+        film.set_samples(samples);
+    }
 
-const THREAD_COUNT: usize = 4;
-const BATCH_SIZE: usize = 512;
+    let (width, height) = (film.width() / 2, film.height() / 2);
 
-fn main() {
-    let scene = create_scene();
+    let settings = RenderSettings {
+        tile_size: options.tile_size,
+        tile_order: options.tile_order,
+        checkpoint: options.checkpoint.or(options.resume),
+        checkpoint_interval: options.checkpoint_interval,
+        report_progress: true,
+        ..RenderSettings::default()
+    };
 
     let start = time::Instant::now();
 
-    let res = 800;
-    let image = trace_scene(Arc::new(scene), res, res);
+    let output = cnol_ray_tracer::render(Arc::new(scene), film, &settings);
+
+    for (path, error) in output.checkpoint_errors {
+        eprintln!("Failed to save checkpoint {}: {}", path.display(), error);
+    }
+
+    let image = downsample(&output.film, width, height);
 
     let end = time::Instant::now();
     let duration = end - start;
@@ -60,6 +96,88 @@ fn main() {
     image.save("out.png").unwrap();
 }
 
+
+const USAGE: &str = "\
+Usage: cnol-ray-tracer [options]
+
+Options:
+    --resolution <pixels>      Width and height of the image (default 800)
+    --samples <count>          Samples per pixel (default 1)
+    --seed <seed>              Seed of the random sampler (default 0)
+    --tile-size <pixels>       Width and height of the tiles rendered by each thread (default 32)
+    --tile-order <order>       Either scanline, hilbert or spiral (default scanline)
+    --checkpoint <file>        Periodically save the render progress to <file>
+    --checkpoint-interval <s>  Seconds between checkpoints (default 60)
+    --resume <file>            Continue the render saved in <file>, using its resolution,
+                               seed and sample count unless --samples is given
+    --preview                  Only render light straight from the lights, with highlights
+    --help                     Print this message";
+
+struct Options {
+    resolution: Option<u32>,
+    samples: Option<u32>,
+    seed: Option<u64>,
+    tile_size: u32,
+    tile_order: TileOrder,
+    checkpoint: Option<PathBuf>,
+    checkpoint_interval: time::Duration,
+    resume: Option<PathBuf>,
+    preview: bool,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        resolution: None,
+        samples: None,
+        seed: None,
+        tile_size: 32,
+        tile_order: TileOrder::Scanline,
+        checkpoint: None,
+        checkpoint_interval: time::Duration::from_secs(60),
+        resume: None,
+        preview: false,
+    };
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+
+        match arg.as_str() {
+            "--resolution" => options.resolution = Some(parse_number(&value()?)?),
+            "--samples" => options.samples = Some(parse_number(&value()?)?),
+            "--seed" => options.seed = Some(parse_number(&value()?)?),
+            "--tile-size" => options.tile_size = parse_number(&value()?)?,
+            "--tile-order" => options.tile_order = value()?.parse()?,
+            "--checkpoint" => options.checkpoint = Some(PathBuf::from(value()?)),
+            "--checkpoint-interval" => {
+                options.checkpoint_interval = time::Duration::from_secs(parse_number(&value()?)?)
+            },
+            "--resume" => options.resume = Some(PathBuf::from(value()?)),
+            "--preview" => options.preview = true,
+            "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            },
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    if options.tile_size == 0 {
+        return Err("The tile size must be positive".to_string());
+    }
+
+    if options.resume.is_some() && (options.resolution.is_some() || options.seed.is_some()) {
+        return Err("The resolution and seed of a resumed render cannot be changed".to_string());
+    }
+
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("Invalid number: {}", text))
+}
+
 fn create_scene() -> Scene {
     let mut scene = Scene::new();
 
@@ -106,34 +224,34 @@ fn create_objects(scene: &mut Scene) {
 
 fn create_walls(scene: &mut Scene) {
     // Floor
-    let shape = Shape::Plane(Plane {
+    let shape = Plane {
         origin: Vector3::new(0.0, -3.0, 0.0),
         normal: Vector3::new(0.0, 1.0, 0.0),
-    });
+    };
     let material = Material::new(Color::new(1.0, 1.0, 1.0), 0.1, 0.3);
     scene.add_object(shape, material);
 
     // Ceiling
-    let shape = Shape::Plane(Plane {
+    let shape = Plane {
         origin: Vector3::new(0.0, 3.0, 0.0),
         normal: Vector3::new(0.0, -1.0, 0.0),
-    });
+    };
     let material = Material::new(Color::new(0.0, 0.0, 1.0), 0.1, 0.3);
     scene.add_object(shape, material);
 
     // Right wall
-    let shape = Shape::Plane(Plane {
+    let shape = Plane {
         origin: Vector3::new(3.0, 0.0, 0.0),
         normal: Vector3::new(-1.0, 0.0, 0.0),
-    });
+    };
     let material = Material::new(Color::new(0.0, 1.0, 0.0), 0.1, 0.3);
     scene.add_object(shape, material);
 
     // Left wall
-    let wall = Shape::Plane(Plane {
+    let wall = Plane {
         origin: Vector3::new(-3.0, 0.0, 0.0),
         normal: Vector3::new(1.0, 0.0, 0.0),
-    });
+    };
 
     let a = sphere([-2.5, 0.0, 5.0], 0.7);
     let b = sphere([-4.0, 0.0, 5.0], 1.0);
@@ -145,213 +263,79 @@ fn create_walls(scene: &mut Scene) {
     scene.add_object(shape, material);
 
     // Back wall
-    let shape = Shape::Plane(Plane {
+    let shape = Plane {
         origin: Vector3::new(0.0, 0.0, 7.0),
         normal: Vector3::new(0.0, 0.0, -1.0),
-    });
+    };
     let material = Material::new(Color::new(1.0, 0.0, 1.0), 0.1, 0.3);
     scene.add_object(shape, material);
 
     // Front wall
-    let shape = Shape::Plane(Plane {
+    let shape = Plane {
         origin: Vector3::new(0.0, 0.0, -1.0),
         normal: Vector3::new(0.0, 0.0, 1.0),
-    });
+    };
     let material = Material::new(Color::new(1.0, 1.0, 1.0), 0.1, 0.3);
     scene.add_object(shape, material);
 }
 
 fn create_lights(scene: &mut Scene) {
     // Light
-    let light = Light::Point(PointLight {
+    let light = PointLight {
         point: Vector3::new(-2.2, 2.2, 2.0),
         color: Color::white(),
-        size: 0.2
-    });
+        size: 0.2,
+        power: Power::Strength(1.2),
+        falloff: Falloff::Linear
+    };
     scene.add_light(light);
 
-    let light = Light::Point(PointLight {
+    let light = PointLight {
         point: Vector3::new(-1.0, 0.2, 1.0),
         color: Color::white(),
-        size: 0.4
-    });
+        size: 0.4,
+        power: Power::Strength(1.2),
+        falloff: Falloff::Linear
+    };
     scene.add_light(light);
 }
 
-fn sphere(center: [f64; 3], radius: f64) -> Shape {
-    Shape::Sphere(Sphere {
+fn sphere(center: [f64; 3], radius: f64) -> Sphere {
+    Sphere {
         center: Vector3 {
             x: center[0],
             y: center[1],
             z: center[2],
         },
         radius
-    })
-}
-
-fn intersection(a: Shape, b: Shape) -> Shape {
-    Shape::Intersection(
-        Box::new(a),
-        Box::new(b)
-    )
-}
-
-fn difference(a: Shape, b: Shape) -> Shape {
-    Shape::Difference(
-        Box::new(a),
-        Box::new(b)
-    )
-}
-
-
-fn trace_scene(scene: Arc<Scene>, image_width: u32, image_height: u32) -> DynamicImage {
-    // Render at twice the scale and downsample
-    let (render_width, render_height) = (image_width * 2, image_height * 2);
-
-    let pixels = Arc::new(Mutex::new(get_pixels(render_width, render_height)));
-
-    let (sender, receiver) = mpsc::channel();
-
-    let mut threads = Vec::new();
-
-    for _ in 0..THREAD_COUNT {
-        let pixels = pixels.clone();
-        let sender = sender.clone();
-        let scene = scene.clone();
-
-        threads.push(thread::spawn(move || {
-            process_pixels(pixels, sender, scene, render_width, render_height);
-        }));
-    }
-
-    let image = receive_image(receiver, render_width, render_height);
-
-    println!("Waiting for threads to join...");
-    for thread in threads {
-        thread.join().unwrap();
-    }
-
-    image.resize(image_width, image_height, image::FilterType::Triangle)
-}
-
-
-fn get_pixels(width: u32, height: u32) -> Vec<(u32, u32)> {
-    let mut pixels = Vec::new();
-
-    for y in 0..height {
-        for x in 0..width {
-            pixels.push((x, y));
-        }
     }
-
-    pixels
 }
 
-fn get_pixel_batch(pixels: &mut MutexGuard<Vec<(u32, u32)>>, batch_size: usize)
-    -> Vec<(u32, u32)> {
-    if pixels.len() < batch_size {
-        pixels.split_off(0)
-    } else {
-        let index = pixels.len() - batch_size;
-        pixels.split_off(index)
-    }
-}
 
-fn process_pixels(
-    pixels: Arc<Mutex<Vec<(u32, u32)>>>,
-    sender: Sender<Vec<(u32, u32, Color)>>,
-    scene: Arc<Scene>,
-    width: u32, height: u32
-) {
-    loop {
-        let batch = get_pixel_batch(&mut pixels.lock().unwrap(), BATCH_SIZE);
-        if batch.len() == 0 {
-            break;
-        }
-
-        let mut results = Vec::new();
-        for (x, y) in batch {
-            let ray = get_ray_from_screen(x, y, width, height);
+fn downsample(film: &Film, width: u32, height: u32) -> DynamicImage {
+    // Filter the colors multiplied by their alpha, so that the black of transparent pixels does
+    // not bleed into the edges of objects
+    let premultiplied = ImageBuffer::from_fn(film.width(), film.height(), |x, y| {
+        let pixel = film.pixel(x, y);
+        let color = pixel.color();
 
-            let color = scene.trace(ray);
-            results.push((x, y, color));
+        Rgba {
+            data: [color.r as f32, color.g as f32, color.b as f32, pixel.alpha() as f32]
         }
+    });
 
-        sender.send(results).unwrap();
-    }
-}
-
-fn receive_image(
-    receiver: Receiver<Vec<(u32, u32, Color)>>,
-    width: u32, height: u32
-) -> DynamicImage {
-    let mut image = DynamicImage::new_rgb8(width, height);
-
-    let mut remaining_pixels = width * height;
-
-    let start_time = time::Instant::now();
-
-    while let Ok(batch) = receiver.recv() {
-        remaining_pixels -= batch.len() as u32;
-
-        for (x, y, color) in batch {
-            image.put_pixel(x, y, color.into());
-        }
-
-        let current_time = time::Instant::now();
-
-        let percentage = 1.0 - (remaining_pixels as f64 / (width * height) as f64);
-
-        let duration = current_time - start_time;
-        let time_elapsed = duration.as_secs() as f64 / 60.0;
-        let time_remaining = time_elapsed / percentage - time_elapsed;
-
-        println!("{} pixels remaining ({:.2}% in {:.1} minutes, approx. {:.1} minutes left)",
-                 remaining_pixels,
-                 percentage * 100.0,
-                 time_elapsed,
-                 time_remaining);
-
-        if remaining_pixels == 0 {
-            break;
-        }
-    }
-
-    image
-}
-
-
-
-// Hjälp med den linjära algebran: https://www.scratchapixel
-// .com/lessons/3d-basic-rendering/ray-tracing-generating-camera-rays
-// /generating-camera-rays
-fn get_ray_from_screen(x: u32, y: u32, width: u32, height: u32) -> Ray {
-    let fov = 70.0f64.to_radians();
-    let aspect_ratio = (width as f64) / (height as f64);
-
-    let normal_x = 2.0 * (x as f64) / (width as f64) - 1.0;
-    let normal_y = 1.0 - 2.0 * (y as f64) / (height as f64);
-
-    let world_height = (fov / 2.0).tan();
-
-    let direction_x = normal_x * aspect_ratio * world_height;
-    let direction_y = normal_y * world_height;
-
+    let resized = imageops::resize(&premultiplied, width, height, FilterType::Triangle);
 
-    let origin = Vector3 {
-        x: 0.0,
-        y: 0.0,
-        z: -1.0,
-    };
+    DynamicImage::ImageRgba8(ImageBuffer::from_fn(width, height, |x, y| {
+        let [r, g, b, alpha] = resized.get_pixel(x, y).data;
+        let alpha = f64::from(alpha);
 
-    let direction = Vector3 {
-        x: direction_x,
-        y: direction_y,
-        z: 1.0,
-    };
+        let color = if alpha > 0.0 {
+            Color::new(f64::from(r), f64::from(g), f64::from(b)).apply_brightness(1.0 / alpha)
+        } else {
+            Color::black()
+        };
 
-    Ray {
-        origin,
-        direction: direction.normal()
-    }
+        color.to_rgba(alpha)
+    }))
 }
@@ -1,19 +1,556 @@
 use color::Color;
+use vector::Vector3;
+use microfacet;
+use texture::{
+    Texture,
+    SurfacePoint,
+    NormalMap
+};
+use sampler;
+
+pub use microfacet::Conductor;
+
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Material {
+    pub color: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    pub reflection: Reflection,
+    pub normal_map: Option<NormalMap>,
+
+    /// Shade the surface with one of two other materials instead
+    pub mix: Option<Mix>,
+
+    pub clear_coat: Option<ClearCoat>,
+
+    /// Let diffuse light travel through the inside of the object, as in skin, wax and milk
+    pub subsurface: Option<Subsurface>,
+
+    /// The color of the light given off by the material
+    pub emission: Color,
+    pub emission_strength: f64,
+
+    /// Makes the object a matte, for compositing the render onto other images
+    pub matte: Option<Matte>
+}
+
+/// How a material divides light between mirror-like reflections and diffuse light
+#[derive(Debug, Clone)]
+pub enum Reflection {
+    /// Mirror-like reflections are added on top of the diffuse color, equally strong at every
+    /// angle
+    Reflectiveness(Arc<dyn Texture>),
+
+    /// The metallic-roughness model of glTF: the color is the diffuse color of dielectrics
+    /// and the reflectance of metals, and reflections strengthen at grazing angles
+    // https://github.com/KhronosGroup/glTF/tree/master/specification/2.0#appendix-b-brdf-implementation
+    MetallicRoughness {
+        metallic: Arc<dyn Texture>,
+
+        /// The reflectance of dielectrics, where 0.5 gives the usual 4% when facing the light
+        specular: Arc<dyn Texture>,
+
+        /// Used instead of the color for the reflectance of metals
+        conductor: Option<Conductor>
+    }
+}
+
+/// A blend of two materials. Each point is shaded with one of them, chosen at random, so
+/// the blend is exact once enough samples are averaged.
+#[derive(Debug, Clone)]
+pub struct Mix {
+    pub first: Arc<Material>,
+    pub second: Arc<Material>,
+
+    /// The fraction of the second material
+    pub factor: Arc<dyn Texture>
+}
+
+/// A thin, clear layer of varnish over a material, as on car paint. It reflects like glass,
+/// and the light it reflects does not reach the material below.
+#[derive(Debug, Clone)]
+pub struct ClearCoat {
+    /// How much of the layer covers the material, from 0 to 1
+    pub strength: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>
+}
+
+/// Light that enters the surface and scatters around inside the object before leaving it
+/// again, often somewhere else. The color of the material gives the color of the light that
+/// leaves, once it has scattered many times.
+#[derive(Debug, Clone, Copy)]
+pub struct Subsurface {
+    /// The average distance red, green and blue light travel inside the object before they
+    /// scatter. Skin, for example, lets red light travel much further than blue light. Every
+    /// channel must be positive.
+    pub mean_free_path: Color
+}
+
+/// The properties of a material at a point on its surface
+#[derive(Debug, Clone, Copy)]
+pub struct Shading {
+    /// The color of diffusely reflected light
     pub color: Color,
+
     pub roughness: f64,
-    pub reflectiveness: f64
+    pub fresnel: Fresnel,
+
+    pub coat: Option<Coat>,
+    pub subsurface: Option<Subsurface>,
+
+    /// The normal used for shading, which may differ from the normal of the surface
+    pub normal: Vector3
+}
+
+/// The properties of a clear coat at a point
+#[derive(Debug, Clone, Copy)]
+pub struct Coat {
+    pub strength: f64,
+    pub roughness: f64
+}
+
+/// The fraction of light reflected like a mirror, depending on the angle it arrives at
+#[derive(Debug, Clone, Copy)]
+pub enum Fresnel {
+    Constant(f64),
+
+    /// A blend between a dielectric and a metal
+    MetallicRoughness {
+        /// The reflectance of the dielectric when facing the light
+        dielectric: f64,
+
+        metal: Metal,
+        metallic: f64
+    }
+}
+
+/// The reflectance of a metal
+#[derive(Debug, Clone, Copy)]
+pub enum Metal {
+    /// Schlick's approximation, from the reflectance when facing the light
+    Schlick(Color),
+
+    Conductor(Conductor)
+}
+
+/// Objects that stand in for parts of another image the render is composited onto. Mattes
+/// only have an effect on rays from the camera; other rays see them as ordinary objects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Matte {
+    /// Cuts a transparent hole into the image, hiding everything behind the object
+    Holdout,
+
+    /// The object is invisible, except for the shadows and reflections falling on it, which
+    /// are recorded in the alpha channel
+    ShadowCatcher
 }
 
 
 impl Material {
     pub fn new(color: Color, roughness: f64, reflectiveness: f64) -> Material {
         Material {
+            color: Arc::new(color),
+            roughness: Arc::new(roughness),
+            reflection: Reflection::Reflectiveness(Arc::new(reflectiveness)),
+            normal_map: None,
+            mix: None,
+            clear_coat: None,
+            subsurface: None,
+            emission: Color::black(),
+            emission_strength: 0.0,
+            matte: None,
+        }
+    }
+
+    pub fn with_color<T: Texture + 'static>(mut self, texture: T) -> Material {
+        self.color = Arc::new(texture);
+        self
+    }
+
+    pub fn with_roughness<T: Texture + 'static>(mut self, texture: T) -> Material {
+        self.roughness = Arc::new(texture);
+        self
+    }
+
+    /// A material following the metallic-roughness model of glTF
+    pub fn metallic_roughness(base_color: Color, metallic: f64, roughness: f64) -> Material {
+        Material {
+            reflection: Reflection::MetallicRoughness {
+                metallic: Arc::new(metallic),
+                specular: Arc::new(0.5),
+                conductor: None,
+            },
+            ..Material::new(base_color, roughness, 0.0)
+        }
+    }
+
+    /// A metal with the reflectance given by its index of refraction, such as
+    /// `Conductor::gold()`
+    pub fn metal(conductor: Conductor, roughness: f64) -> Material {
+        Material::metallic_roughness(Color::white(), 1.0, roughness).with_conductor(conductor)
+    }
+
+    pub fn with_reflectiveness<T: Texture + 'static>(mut self, texture: T) -> Material {
+        self.reflection = Reflection::Reflectiveness(Arc::new(texture));
+        self
+    }
+
+    /// Set how metallic the material is, switching to the metallic-roughness model
+    pub fn with_metallic<T: Texture + 'static>(mut self, texture: T) -> Material {
+        let reflection = self.metallic_roughness_mut();
+
+        if let Reflection::MetallicRoughness { ref mut metallic, .. } = *reflection {
+            *metallic = Arc::new(texture);
+        }
+
+        self
+    }
+
+    /// Set the reflectance of dielectrics, switching to the metallic-roughness model
+    pub fn with_specular<T: Texture + 'static>(mut self, texture: T) -> Material {
+        let reflection = self.metallic_roughness_mut();
+
+        if let Reflection::MetallicRoughness { ref mut specular, .. } = *reflection {
+            *specular = Arc::new(texture);
+        }
+
+        self
+    }
+
+    /// Set the index of refraction of metals, switching to the metallic-roughness model
+    pub fn with_conductor(mut self, conductor: Conductor) -> Material {
+        let reflection = self.metallic_roughness_mut();
+
+        if let Reflection::MetallicRoughness { conductor: ref mut c, .. } = *reflection {
+            *c = Some(conductor);
+        }
+
+        self
+    }
+
+    pub fn with_normal_map(mut self, normal_map: NormalMap) -> Material {
+        self.normal_map = Some(normal_map);
+        self
+    }
+
+    /// Blend two materials by a factor or a mask, such as a texture of rust on metal. Their
+    /// emission is blended too, while the matte of the first material is kept. Each material
+    /// keeps its own clear coat, and a clear coat added to the blend covers both.
+    pub fn mix<T: Texture + 'static>(first: Material, second: Material, factor: T) -> Material {
+        Material {
+            mix: Some(Mix {
+                first: Arc::new(first.clone()),
+                second: Arc::new(second),
+                factor: Arc::new(factor),
+            }),
+            clear_coat: None,
+            subsurface: None,
+            emission: Color::black(),
+            emission_strength: 0.0,
+            ..first
+        }
+    }
+
+    /// Cover the material with a clear coat, with a reflectance of 4% when facing the light
+    pub fn with_clear_coat<S, R>(mut self, strength: S, roughness: R) -> Material
+        where S: Texture + 'static, R: Texture + 'static {
+        self.clear_coat = Some(ClearCoat {
+            strength: Arc::new(strength),
+            roughness: Arc::new(roughness),
+        });
+
+        self
+    }
+
+    /// A translucent material, such as wax, with light scattering inside it. The scatter
+    /// color is the color of the light leaving the object once it has scattered many times.
+    pub fn subsurface(scatter_color: Color, mean_free_path: Color, roughness: f64) -> Material {
+        Material::metallic_roughness(scatter_color, 0.0, roughness).with_subsurface(mean_free_path)
+    }
+
+    /// Let light scatter inside the object. Panics unless the mean free path is positive and
+    /// finite in every channel.
+    pub fn with_subsurface(mut self, mean_free_path: Color) -> Material {
+        let valid = |distance: f64| distance > 0.0 && distance.is_finite();
+
+        assert!(
+            valid(mean_free_path.r) && valid(mean_free_path.g) && valid(mean_free_path.b),
+            "the mean free path must be positive and finite, not {:?}",
+            mean_free_path
+        );
+
+        self.subsurface = Some(Subsurface { mean_free_path });
+        self
+    }
+
+    /// Evaluate the textures of the material at a point.
+    ///
+    /// Mixes choose one of their materials at random, using the sampler of the current
+    /// sample, so evaluating the same point twice may give the shading of different materials.
+    /// Callers that need the same shading twice should keep the result.
+    pub fn shading(&self, point: &SurfacePoint) -> Shading {
+        let mut shading = match self.mix {
+            Some(ref mix) if sampler::gen_range(0.0, 1.0) < mix.factor.value(point) => {
+                mix.second.shading(point)
+            },
+            Some(ref mix) => mix.first.shading(point),
+            None => self.layer_shading(point),
+        };
+
+        if let Some(ref clear_coat) = self.clear_coat {
+            shading.coat = Some(Coat {
+                strength: clear_coat.strength.value(point).clamp(0.0, 1.0),
+                roughness: clear_coat.roughness.value(point),
+            });
+        }
+
+        shading
+    }
+
+    /// Make the material glow
+    pub fn emissive(mut self, emission: Color, strength: f64) -> Material {
+        self.emission = emission;
+        self.emission_strength = strength;
+
+        self
+    }
+
+    /// A material that hides everything behind it
+    pub fn holdout() -> Material {
+        Material {
+            matte: Some(Matte::Holdout),
+            ..Material::new(Color::black(), 1.0, 0.0)
+        }
+    }
+
+    /// A material that only shows the shadows falling on it, and reflections in proportion
+    /// to its reflectiveness
+    pub fn shadow_catcher(color: Color, roughness: f64, reflectiveness: f64) -> Material {
+        Material {
+            matte: Some(Matte::ShadowCatcher),
+            ..Material::new(color, roughness, reflectiveness)
+        }
+    }
+
+    /// The light given off by the material
+    /// The light given off at a point of the surface
+    pub fn emitted(&self, point: &SurfacePoint) -> Color {
+        let emitted = self.emission.apply_brightness(self.emission_strength);
+
+        match self.mix {
+            Some(ref mix) => {
+                let factor = mix.factor.value(point).clamp(0.0, 1.0);
+                emitted + mix.first.emitted(point).mix(mix.second.emitted(point), factor)
+            },
+            None => emitted,
+        }
+    }
+
+    /// The light given off by the surface, if it is the same everywhere
+    pub fn uniform_emission(&self) -> Option<Color> {
+        let emitted = self.emission.apply_brightness(self.emission_strength);
+
+        match self.mix {
+            Some(ref mix) => {
+                let first = mix.first.uniform_emission()?;
+                let second = mix.second.uniform_emission()?;
+
+                let same = first.r == second.r && first.g == second.g && first.b == second.b;
+                if same {
+                    Some(emitted + first)
+                } else {
+                    None
+                }
+            },
+            None => Some(emitted),
+        }
+    }
+
+    /// Test if any part of the surface gives off light
+    pub fn is_emissive(&self) -> bool {
+        let emitted = self.emission.apply_brightness(self.emission_strength);
+        let layers_emit = self.mix.as_ref()
+            .is_some_and(|mix| mix.first.is_emissive() || mix.second.is_emissive());
+
+        emitted.r > 0.0 || emitted.g > 0.0 || emitted.b > 0.0 || layers_emit
+    }
+
+    /// Evaluate the textures of the material itself, without its mix or clear coat
+    fn layer_shading(&self, point: &SurfacePoint) -> Shading {
+        let color = self.color.color(point);
+
+        let (color, fresnel) = match self.reflection {
+            Reflection::Reflectiveness(ref reflectiveness) => {
+                (color, Fresnel::Constant(reflectiveness.value(point)))
+            },
+            Reflection::MetallicRoughness { ref metallic, ref specular, conductor } => {
+                let metallic = metallic.value(point).clamp(0.0, 1.0);
+
+                let metal = match conductor {
+                    Some(conductor) => Metal::Conductor(conductor),
+                    None => Metal::Schlick(color),
+                };
+
+                let fresnel = Fresnel::MetallicRoughness {
+                    dielectric: 0.08 * specular.value(point),
+                    metal,
+                    metallic,
+                };
+
+                // Metals have no diffuse light
+                (color.apply_brightness(1.0 - metallic), fresnel)
+            },
+        };
+
+        Shading {
             color,
-            roughness,
-            reflectiveness
+            roughness: self.roughness.value(point),
+            fresnel,
+            coat: None,
+            subsurface: self.subsurface,
+            normal: match self.normal_map {
+                Some(ref normal_map) => normal_map.normal(point),
+                None => point.normal,
+            },
+        }
+    }
+
+    /// Switch to the metallic-roughness model if the material does not use it yet, keeping
+    /// the other properties of the material
+    fn metallic_roughness_mut(&mut self) -> &mut Reflection {
+        if let Reflection::Reflectiveness(_) = self.reflection {
+            self.reflection = Reflection::MetallicRoughness {
+                metallic: Arc::new(0.0),
+                specular: Arc::new(0.5),
+                conductor: None,
+            };
+        }
+
+        &mut self.reflection
+    }
+}
+
+
+impl Shading {
+    /// The fraction of light reflected by the clear coat, given the cosine of the angle
+    /// between the light and the normal
+    pub fn coat_reflectance(&self, cos: f64) -> f64 {
+        match self.coat {
+            Some(coat) => coat.strength * microfacet::schlick(0.04, cos),
+            None => 0.0,
+        }
+    }
+}
+
+
+impl Subsurface {
+    /// Find the fraction of light kept each time it scatters, that makes the light leaving
+    /// the object after many scattering events the given color
+    // Chiang, Kutz and Burley, "Practical and Controllable Subsurface Scattering for
+    // Production Path Tracing", 2016
+    pub fn single_scattering_albedo(color: Color) -> Color {
+        let invert = |color: f64| {
+            let color = color.clamp(0.0, 1.0);
+            let t = 4.09712 + 4.20863 * color -
+                (9.59217 + 41.6808 * color + 17.7126 * color * color).sqrt();
+
+            1.0 - t * t
+        };
+
+        Color::new(invert(color.r), invert(color.g), invert(color.b))
+    }
+}
+
+
+impl Fresnel {
+    /// The fraction of light reflected by a surface, or by a microfacet on it, given the cosine
+    /// of the angle between the light and its normal
+    pub fn reflectance(&self, cos: f64) -> Color {
+        match *self {
+            Fresnel::Constant(reflectance) => Color::new(reflectance, reflectance, reflectance),
+            Fresnel::MetallicRoughness { dielectric, metal, metallic } => {
+                let dielectric = microfacet::schlick(dielectric, cos);
+
+                let metal = match metal {
+                    Metal::Schlick(color) => Color::new(
+                        microfacet::schlick(color.r, cos),
+                        microfacet::schlick(color.g, cos),
+                        microfacet::schlick(color.b, cos)
+                    ),
+                    Metal::Conductor(conductor) => conductor.reflectance(cos),
+                };
+
+                Color::new(dielectric, dielectric, dielectric).mix(metal, metallic)
+            },
+        }
+    }
+
+    /// The fraction of light that is not reflected like a mirror, and is left to be diffusely
+    /// reflected. Constant reflections are added on top of the diffuse light instead.
+    pub fn diffuse_fraction(&self, cos: f64) -> f64 {
+        match *self {
+            Fresnel::Constant(_) => 1.0,
+            Fresnel::MetallicRoughness { dielectric, .. } => {
+                1.0 - microfacet::schlick(dielectric, cos)
+            },
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chooses the second material where `x` is positive
+    #[derive(Debug)]
+    struct RightHalf;
+
+    impl Texture for RightHalf {
+        fn color(&self, point: &SurfacePoint) -> Color {
+            let value = self.value(point);
+            Color::new(value, value, value)
+        }
+
+        fn value(&self, point: &SurfacePoint) -> f64 {
+            if point.position.x > 0.0 { 1.0 } else { 0.0 }
+        }
+    }
+
+    fn point_at(x: f64) -> SurfacePoint {
+        SurfacePoint {
+            position: Vector3::new(x, 0.0, 0.0),
+            object_position: Vector3::new(x, 0.0, 0.0),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            uv: (0.0, 0.0),
+            tangent: Vector3::new(1.0, 0.0, 0.0),
+            bitangent: Vector3::new(0.0, 0.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn mixed_emission_follows_the_mask() {
+        let glowing = Material::new(Color::black(), 1.0, 0.0)
+            .emissive(Color::new(1.0, 0.5, 0.25), 2.0);
+        let plain = Material::new(Color::new(0.5, 0.5, 0.5), 1.0, 0.0);
+
+        let masked = Material::mix(glowing.clone(), plain.clone(), RightHalf);
+
+        assert_eq!(masked.emitted(&point_at(-1.0)).r, 2.0);
+        assert_eq!(masked.emitted(&point_at(1.0)).r, 0.0);
+        assert!(masked.is_emissive());
+        assert!(masked.uniform_emission().is_none());
+
+        let blended = Material::mix(plain, glowing, 0.25);
+        assert_eq!(blended.emitted(&point_at(0.0)).g, 0.25);
+    }
+
+    #[test]
+    fn mixing_the_same_emission_keeps_it_uniform() {
+        let glow = |color| Material::new(color, 1.0, 0.0).emissive(Color::new(1.0, 1.0, 1.0), 3.0);
+        let mixed = Material::mix(glow(Color::black()), glow(Color::new(1.0, 0.0, 0.0)), RightHalf);
+
+        assert_eq!(mixed.uniform_emission().map(|color| color.b), Some(3.0));
+    }
+}
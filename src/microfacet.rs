@@ -0,0 +1,221 @@
+use vector::Vector3;
+use color::Color;
+
+use std::f64::consts::PI;
+
+/// The narrowest highlights, as the exponent of a perfect mirror would be infinite. Narrower
+/// highlights, such as those of polished metal, only show up as fireflies in a preview.
+const MIN_HIGHLIGHT_ALPHA: f64 = 0.1;
+
+/// The GGX distribution of microfacet normals, with Smith shadowing and masking. Directions
+/// are given in a local frame where the surface normal is the z-axis.
+// https://www.cs.cornell.edu/~srm/publications/EGSR07-btdf.pdf
+#[derive(Debug, Clone, Copy)]
+pub struct Ggx {
+    /// The width of the distribution, where 0 is a perfect mirror
+    pub alpha: f64
+}
+
+/// Blinn-Phong highlights, normalized so that they keep their brightness as they change size.
+/// They are cheap enough to evaluate for every light, instead of sampling reflections.
+#[derive(Debug, Clone, Copy)]
+pub struct BlinnPhong {
+    pub exponent: f64
+}
+
+/// A metal, described by its complex index of refraction `eta + i k` for red, green and blue
+/// light
+#[derive(Debug, Clone, Copy)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color
+}
+
+
+impl Ggx {
+    /// Map a perceptual roughness, where 0 is a mirror and 1 is fully rough, to a distribution
+    /// whose highlights change size evenly with the roughness
+    pub fn from_roughness(roughness: f64) -> Ggx {
+        let roughness = roughness.clamp(0.0, 1.0);
+
+        Ggx {
+            alpha: roughness * roughness,
+        }
+    }
+
+    /// The fraction of microfacets visible from a direction
+    pub fn masking(&self, direction: Vector3) -> f64 {
+        1.0 / (1.0 + self.lambda(direction))
+    }
+
+    /// The fraction of microfacets visible from both directions
+    pub fn shadowing_masking(&self, view: Vector3, light: Vector3) -> f64 {
+        1.0 / (1.0 + self.lambda(view) + self.lambda(light))
+    }
+
+    /// Choose a microfacet normal visible from a direction, with a probability proportional to
+    /// how much of the view it covers
+    // http://jcgt.org/published/0007/04/01/paper.pdf
+    pub fn sample_visible_normal(&self, view: Vector3, u1: f64, u2: f64) -> Vector3 {
+        // Stretch the view so that the distribution becomes a hemisphere
+        let view = Vector3::new(self.alpha * view.x, self.alpha * view.y, view.z).normal();
+
+        let length2 = view.x * view.x + view.y * view.y;
+        let t1 = if length2 > 0.0 {
+            Vector3::new(-view.y, view.x, 0.0) / length2.sqrt()
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = Vector3::cross(view, t1);
+
+        // Pick a point on the projected hemisphere
+        let radius = u1.sqrt();
+        let angle = 2.0 * PI * u2;
+        let p1 = radius * angle.cos();
+        let p2 = radius * angle.sin();
+
+        let s = 0.5 * (1.0 + view.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * p2;
+        let p3 = (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        let normal = p1 * t1 + p2 * t2 + p3 * view;
+
+        // Unstretch the normal
+        Vector3::new(self.alpha * normal.x, self.alpha * normal.y, normal.z.max(0.0)).normal()
+    }
+
+    // https://jcgt.org/published/0003/02/03/paper.pdf
+    fn lambda(&self, direction: Vector3) -> f64 {
+        let cos2 = direction.z * direction.z;
+
+        if cos2 <= 0.0 {
+            return f64::INFINITY;
+        }
+
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+}
+
+
+impl BlinnPhong {
+    /// Find the exponent whose highlights match those of GGX with the same roughness
+    // Walter et al., "Microfacet Models for Refraction through Rough Surfaces", 2007
+    pub fn from_roughness(roughness: f64) -> BlinnPhong {
+        let alpha = Ggx::from_roughness(roughness).alpha.max(MIN_HIGHLIGHT_ALPHA);
+
+        BlinnPhong {
+            exponent: 2.0 / (alpha * alpha) - 2.0,
+        }
+    }
+
+    /// The brightness of the highlight, relative to a diffuse surface, given the cosine of the
+    /// angle between the normal and the halfway vector between the light and the view
+    pub fn highlight(&self, cos: f64) -> f64 {
+        if cos <= 0.0 {
+            return 0.0;
+        }
+
+        (self.exponent + 8.0) / 8.0 * cos.powf(self.exponent)
+    }
+}
+
+
+// The indices of refraction at 650, 550 and 450 nm
+// https://refractiveindex.info
+impl Conductor {
+    pub fn gold() -> Conductor {
+        Conductor {
+            eta: Color::new(0.143, 0.374, 1.442),
+            k: Color::new(3.983, 2.385, 1.603),
+        }
+    }
+
+    pub fn copper() -> Conductor {
+        Conductor {
+            eta: Color::new(0.200, 0.924, 1.102),
+            k: Color::new(3.912, 2.452, 2.142),
+        }
+    }
+
+    pub fn aluminum() -> Conductor {
+        Conductor {
+            eta: Color::new(1.657, 0.880, 0.521),
+            k: Color::new(9.224, 6.270, 4.837),
+        }
+    }
+
+    pub fn silver() -> Conductor {
+        Conductor {
+            eta: Color::new(0.155, 0.117, 0.138),
+            k: Color::new(4.828, 3.122, 2.147),
+        }
+    }
+
+    /// The fraction of light reflected, given the cosine of the angle between the light and
+    /// the normal
+    pub fn reflectance(&self, cos: f64) -> Color {
+        Color::new(
+            fresnel_conductor(cos, self.eta.r, self.k.r),
+            fresnel_conductor(cos, self.eta.g, self.k.g),
+            fresnel_conductor(cos, self.eta.b, self.k.b)
+        )
+    }
+}
+
+
+/// Schlick's approximation of the fraction of light reflected, given the reflectance when the
+/// light hits the surface head-on and the cosine of the angle between the light and the normal
+pub fn schlick(reflectance: f64, cos: f64) -> f64 {
+    reflectance + (1.0 - reflectance) * (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+}
+
+/// The exact fraction of unpolarized light reflected by a conductor
+// http://www.pbr-book.org/3ed-2018/Reflection_Models/Specular_Reflection_and_Transmission.html
+fn fresnel_conductor(cos: f64, eta: f64, k: f64) -> f64 {
+    let cos = cos.clamp(0.0, 1.0);
+    let cos2 = cos * cos;
+    let sin2 = 1.0 - cos2;
+
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos * a;
+    let perpendicular = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let parallel = perpendicular * (t3 - t4) / (t3 + t4);
+
+    0.5 * (parallel + perpendicular)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gold_reflects_the_pbrt_values_head_on() {
+        // ((eta - 1)^2 + k^2) / ((eta + 1)^2 + k^2) for the indices of refraction of gold
+        let reflectance = Conductor::gold().reflectance(1.0);
+
+        assert!((reflectance.r - 0.9667).abs() < 1.0e-3);
+        assert!((reflectance.g - 0.8025).abs() < 1.0e-3);
+        assert!((reflectance.b - 0.3240).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn conductors_reflect_everything_at_grazing_angles() {
+        let reflectance = Conductor::gold().reflectance(0.0);
+
+        assert!((reflectance.r - 1.0).abs() < 1.0e-9);
+        assert!((reflectance.g - 1.0).abs() < 1.0e-9);
+        assert!((reflectance.b - 1.0).abs() < 1.0e-9);
+    }
+}
@@ -4,13 +4,28 @@ use vector::Vector3;
 pub struct Intersection {
     pub point: Vector3,
     pub normal: Vector3,
-    pub distance: f64
+    pub distance: f64,
+
+    /// The surface coordinates of the point
+    pub uv: (f64, f64),
+
+    /// How the point moves along the surface as `u` increases, so that its length is the
+    /// distance covered by one unit of `u`
+    pub tangent: Vector3,
+
+    /// How the point moves along the surface as `v` increases
+    pub bitangent: Vector3,
+
+    /// Which of the primitives that make up a composite shape the point lies on, counted
+    /// from the left. It is 0 for shapes that are not composite.
+    pub leaf: usize
 }
 
 pub type EntryExit = (Intersection, Intersection);
 
 impl Intersection {
-    /// Inverts the normal
+    /// Inverts the normal. The tangent and bitangent are kept, as the surface coordinates
+    /// still change the same way along the surface.
     pub fn inverse(self) -> Intersection {
         Intersection {
             normal: -self.normal,
@@ -1,7 +1,3 @@
-use rand;
-use rand::Rng;
-
-
 mod intersection;
 
 pub use self::intersection::{
@@ -10,6 +6,11 @@ pub use self::intersection::{
 };
 
 use vector::Vector3;
+use microfacet::Ggx;
+use sampler;
+
+/// The width of the narrowest microfacet distribution that is not treated as a mirror
+const MIN_ALPHA: f64 = 1.0e-4;
 
 #[derive(Debug)]
 pub struct Ray {
@@ -31,26 +32,103 @@ impl Ray {
         }
     }
 
+    /// Reflect the ray off a rough surface, in a direction chosen from the visible normals of
+    /// a GGX distribution. Returns the reflected ray and the fraction of the light along it
+    /// that is reflected, or `None` if the ray is reflected into the surface.
+    pub fn scatter(&self, intersection: Intersection, roughness: f64) -> Option<(Ray, f64)> {
+        let ggx = Ggx::from_roughness(roughness);
+
+        let normal = intersection.normal;
+        let (tangent, bitangent) = normal.perpendicular_axes();
+
+        let to_local = |direction: Vector3| Vector3::new(
+            Vector3::dot(direction, tangent),
+            Vector3::dot(direction, bitangent),
+            Vector3::dot(direction, normal)
+        );
+
+        let view = to_local(-self.direction);
 
-    pub fn scatter(&self, intersection: Intersection, roughness: f64) -> Ray {
-        let mut rng = rand::thread_rng();
+        // Very smooth surfaces are mirrors, and so are surfaces hit from behind
+        if ggx.alpha < MIN_ALPHA || view.z <= 0.0 {
+            return Some((self.bounce(intersection), 1.0));
+        }
 
-        let right = Vector3::cross(self.direction, intersection.normal);
-        let up = Vector3::cross(right, self.direction);
+        let microfacet = ggx.sample_visible_normal(
+            view,
+            sampler::gen_range(0.0, 1.0),
+            sampler::gen_range(0.0, 1.0)
+        );
 
-        let distance_x = if roughness > 0.0 { rng.gen_range(-roughness, roughness) } else {0.0};
-        let distance_y = if roughness > 0.0 { rng.gen_range(-roughness, roughness) } else {0.0};
+        let light = 2.0 * Vector3::dot(view, microfacet) * microfacet - view;
 
-        let mut bounce = self.bounce(intersection);
+        if light.z <= 0.0 {
+            return None;
+        }
 
-        bounce.direction = (
-            bounce.direction +
-            right * distance_x +
-            up * distance_y
-        ).normal();
+        let ray = Ray {
+            origin: intersection.point,
+            direction: (light.x * tangent + light.y * bitangent + light.z * normal).normal(),
+        };
 
-        bounce
+        // The distribution and the masking from the view cancel out with the probability of
+        // choosing the direction
+        Some((ray, ggx.shadowing_masking(view, light) / ggx.masking(view)))
     }
 }
 
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floor_hit() -> Intersection {
+        Intersection {
+            point: Vector3::zero(),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            distance: 1.0,
+            uv: (0.0, 0.0),
+            tangent: Vector3::new(1.0, 0.0, 0.0),
+            bitangent: Vector3::new(0.0, 0.0, 1.0),
+            leaf: 0,
+        }
+    }
+
+    fn ray_at(angle: f64) -> Ray {
+        Ray {
+            origin: Vector3::new(-angle.sin(), angle.cos(), 0.0),
+            direction: Vector3::new(angle.sin(), -angle.cos(), 0.0),
+        }
+    }
+
+    #[test]
+    fn scattering_never_reflects_more_light_than_arrives() {
+        for (i, &roughness) in [0.05, 0.3, 0.6, 1.0].iter().enumerate() {
+            for (j, &angle) in [0.0, 0.5, 1.0, 1.4, 1.55].iter().enumerate() {
+                for sample in 0..1000 {
+                    sampler::seed_sample(0, i as u32, j as u32, sample);
+
+                    if let Some((bounce, weight)) = ray_at(angle).scatter(floor_hit(), roughness) {
+                        assert!((0.0..=1.0 + 1.0e-9).contains(&weight), "weight {}", weight);
+                        assert!(bounce.direction.y > 0.0);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_roughness_is_a_mirror() {
+        for sample in 0..100 {
+            sampler::seed_sample(0, 0, 0, sample);
+
+            let ray = ray_at(0.7);
+            let (bounce, weight) = ray.scatter(floor_hit(), 0.0).unwrap();
+
+            assert!((bounce.direction - ray.bounce(floor_hit()).direction).length() < 1.0e-9);
+            assert_eq!(weight, 1.0);
+        }
+    }
+}
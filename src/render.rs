@@ -0,0 +1,247 @@
+use scene::Scene;
+use camera::Camera;
+use sampler;
+use film::{
+    Film,
+    FilmPixel
+};
+use tile::{
+    self,
+    Tile,
+    TileOrder,
+    TileQueue
+};
+
+use std::io;
+use std::path::PathBuf;
+use std::time;
+use std::thread;
+use std::sync::{
+    Arc,
+    mpsc,
+    mpsc::{
+        Sender,
+        Receiver
+    }
+};
+
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    pub camera: Camera,
+
+    pub thread_count: usize,
+
+    /// The width and height of the tiles, in pixels. A size of 0 is treated as 1.
+    pub tile_size: u32,
+    pub tile_order: TileOrder,
+
+    /// Where to periodically save the film, if anywhere
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: time::Duration,
+
+    /// Print the progress of the render to stdout
+    pub report_progress: bool
+}
+
+/// The film after rendering, along with anything that went wrong without stopping the render
+#[derive(Debug)]
+pub struct RenderOutput {
+    pub film: Film,
+
+    /// The checkpoints that could not be saved, in the order they were attempted
+    pub checkpoint_errors: Vec<(PathBuf, io::Error)>
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            camera: Camera::default(),
+            thread_count: 4,
+            tile_size: 32,
+            tile_order: TileOrder::Scanline,
+            checkpoint: None,
+            checkpoint_interval: time::Duration::from_secs(60),
+            report_progress: false,
+        }
+    }
+}
+
+
+/// Take the remaining samples of every pixel in the film. Failing to save a checkpoint does
+/// not stop the render, but is reported in the output.
+pub fn render(scene: Arc<Scene>, film: Film, settings: &RenderSettings) -> RenderOutput {
+    let tiles = get_unfinished_tiles(&film, settings);
+
+    let total_pixels = tiles.iter().map(Tile::area).sum();
+    let queue = Arc::new(TileQueue::new(tiles));
+    let initial_film = Arc::new(film);
+
+    let (sender, receiver) = mpsc::channel();
+
+    let mut threads = Vec::new();
+
+    for _ in 0..settings.thread_count.max(1) {
+        let queue = queue.clone();
+        let sender = sender.clone();
+        let scene = scene.clone();
+        let film = initial_film.clone();
+        let camera = settings.camera.clone();
+
+        threads.push(thread::spawn(move || {
+            process_tiles(&queue, sender, &scene, &camera, &film);
+        }));
+    }
+
+    let film = Film::clone(&initial_film);
+    let output = receive_film(receiver, film, total_pixels, settings);
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    output
+}
+
+
+/// Get all tiles that contain pixels which still lack samples
+fn get_unfinished_tiles(film: &Film, settings: &RenderSettings) -> Vec<Tile> {
+    let size = settings.tile_size.max(1);
+    let tiles = tile::get_tiles(film.width(), film.height(), size, settings.tile_order);
+
+    tiles.into_iter()
+        .filter(|tile| tile.pixels().any(|(x, y)| film.pixel(x, y).samples < film.samples()))
+        .collect()
+}
+
+fn process_tiles(
+    queue: &TileQueue,
+    sender: Sender<(Tile, Vec<FilmPixel>)>,
+    scene: &Scene,
+    camera: &Camera,
+    film: &Film
+) {
+    while let Some(tile) = queue.next() {
+        let pixels = tile.pixels()
+            .map(|(x, y)| trace_pixel(scene, camera, film, x, y))
+            .collect();
+
+        sender.send((tile, pixels)).unwrap();
+    }
+}
+
+/// Take the remaining samples of a pixel
+fn trace_pixel(scene: &Scene, camera: &Camera, film: &Film, x: u32, y: u32) -> FilmPixel {
+    let mut pixel = film.pixel(x, y);
+
+    while pixel.samples < film.samples() {
+        sampler::seed_sample(film.seed(), x, y, pixel.samples);
+
+        let ray = camera.ray(x, y, film.width(), film.height());
+        pixel.add_sample(scene.trace(ray));
+    }
+
+    pixel
+}
+
+fn receive_film(
+    receiver: Receiver<(Tile, Vec<FilmPixel>)>,
+    mut film: Film,
+    total_pixels: usize,
+    settings: &RenderSettings
+) -> RenderOutput {
+    let mut remaining_pixels = total_pixels;
+    let mut checkpoint_errors = Vec::new();
+
+    let start_time = time::Instant::now();
+    let mut last_checkpoint = start_time;
+
+    while remaining_pixels > 0 {
+        let (tile, pixels) = match receiver.recv() {
+            Ok(result) => result,
+            Err(_) => break,
+        };
+
+        remaining_pixels -= tile.area();
+        film.set_tile(&tile, &pixels);
+
+        let current_time = time::Instant::now();
+
+        if settings.report_progress {
+            let percentage = 1.0 - (remaining_pixels as f64 / total_pixels as f64);
+
+            let duration = current_time - start_time;
+            let time_elapsed = duration.as_secs() as f64 / 60.0;
+            let time_remaining = time_elapsed / percentage - time_elapsed;
+
+            println!("{} pixels remaining ({:.2}% in {:.1} minutes, approx. {:.1} minutes left)",
+                     remaining_pixels,
+                     percentage * 100.0,
+                     time_elapsed,
+                     time_remaining);
+        }
+
+        if current_time - last_checkpoint >= settings.checkpoint_interval {
+            checkpoint_errors.extend(save_checkpoint(&film, settings));
+            last_checkpoint = current_time;
+        }
+    }
+
+    checkpoint_errors.extend(save_checkpoint(&film, settings));
+
+    RenderOutput {
+        film,
+        checkpoint_errors,
+    }
+}
+
+/// Save the film to the checkpoint, if there is one, returning the error if that failed
+fn save_checkpoint(film: &Film, settings: &RenderSettings) -> Option<(PathBuf, io::Error)> {
+    let path = settings.checkpoint.as_ref()?;
+
+    match film.save_checkpoint(path) {
+        Ok(()) => {
+            if settings.report_progress {
+                println!("Saved checkpoint to {}", path.display());
+            }
+
+            None
+        },
+        Err(error) => Some((path.clone(), error)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_tiles_render_single_pixels() {
+        let settings = RenderSettings {
+            tile_size: 0,
+            ..RenderSettings::default()
+        };
+
+        let output = render(Arc::new(Scene::new()), Film::new(3, 2, 0, 1), &settings);
+
+        assert_eq!(output.film.pixel(2, 1).samples, 1);
+    }
+
+    #[test]
+    fn failed_checkpoints_are_reported() {
+        let path = PathBuf::from("/nonexistent-directory/checkpoint");
+
+        let settings = RenderSettings {
+            thread_count: 2,
+            tile_size: 2,
+            checkpoint: Some(path.clone()),
+            ..RenderSettings::default()
+        };
+
+        let output = render(Arc::new(Scene::new()), Film::new(4, 4, 0, 1), &settings);
+
+        assert_eq!(output.film.pixel(3, 3).samples, 1);
+        assert!(!output.checkpoint_errors.is_empty());
+        assert!(output.checkpoint_errors.iter().all(|(error_path, _)| *error_path == path));
+    }
+}
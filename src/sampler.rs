@@ -0,0 +1,88 @@
+use rand::{
+    Rng,
+    SeedableRng,
+    prng::XorShiftRng
+};
+
+use vector::Vector3;
+
+use std::cell::RefCell;
+use std::f64::consts::PI;
+
+thread_local! {
+    static RNG: RefCell<XorShiftRng> = RefCell::new(XorShiftRng::seed_from_u64(0));
+}
+
+
+/// Restart the random sequence of the current thread at the given sample of a pixel.
+///
+/// Every sample gets its own sequence, so the result of a sample does not depend on which
+/// thread renders it, or on whether the render has been resumed from a checkpoint.
+pub fn seed_sample(seed: u64, x: u32, y: u32, sample: u32) {
+    let pixel = (u64::from(x) << 32) | u64::from(y);
+    let hash = mix(mix(mix(seed) ^ pixel) ^ u64::from(sample));
+
+    RNG.with(|rng| *rng.borrow_mut() = XorShiftRng::seed_from_u64(hash));
+}
+
+/// Run a function with its own random sequence, starting from a seed, and then continue the
+/// sequence of the current thread where it was left. Results that are computed once and then
+/// shared, such as estimates, then do not depend on which thread computed them.
+pub fn with_seed<T, F: FnOnce() -> T>(seed: u64, f: F) -> T {
+    let previous = RNG.with(|rng| rng.replace(XorShiftRng::seed_from_u64(seed)));
+    let result = f();
+    RNG.with(|rng| rng.replace(previous));
+
+    result
+}
+
+/// Get a random number in the range [low, high)
+pub fn gen_range(low: f64, high: f64) -> f64 {
+    RNG.with(|rng| rng.borrow_mut().gen_range(low, high))
+}
+
+/// Get a random direction within a cone around an axis, all directions being equally likely
+pub fn cone(axis: Vector3, max_cos: f64) -> Vector3 {
+    let cos_theta = 1.0 - gen_range(0.0, 1.0) * (1.0 - max_cos);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = gen_range(0.0, 2.0 * PI);
+
+    let (tangent, bitangent) = axis.perpendicular_axes();
+
+    axis.normal() * cos_theta +
+        tangent * (sin_theta * phi.cos()) +
+        bitangent * (sin_theta * phi.sin())
+}
+
+/// Get a random direction, all directions being equally likely
+pub fn sphere() -> Vector3 {
+    cone(Vector3::new(0.0, 0.0, 1.0), -1.0)
+}
+
+/// Get a random direction on the side of a plane facing along its normal, with a probability
+/// proportional to the cosine of the angle to the normal
+pub fn cosine_hemisphere(normal: Vector3) -> Vector3 {
+    let (x, y) = disk(1.0);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+
+    let (tangent, bitangent) = normal.perpendicular_axes();
+
+    normal.normal() * z + tangent * x + bitangent * y
+}
+
+/// Get a random point within a disk around the origin, all points being equally likely
+pub fn disk(radius: f64) -> (f64, f64) {
+    let distance = radius * gen_range(0.0, 1.0).sqrt();
+    let angle = gen_range(0.0, 2.0 * PI);
+
+    (distance * angle.cos(), distance * angle.sin())
+}
+
+
+// https://xorshift.di.unimi.it/splitmix64.c
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -1,73 +1,370 @@
 
-type Id = usize;
+pub type Id = usize;
+pub type LightId = usize;
 
-use material::Material;
+use material::{
+    Material,
+    Matte,
+    Shading,
+    Subsurface
+};
+use texture::SurfacePoint;
+use microfacet::BlinnPhong;
+use sampler;
 use shape::Shape;
 use ray::Ray;
 use color::Color;
 use vector::Vector3;
 use ray::Intersection;
-use light::Light;
+use background::Background;
+use film::Sample;
+use light::{
+    Light,
+    LightSample,
+    ShapeLight
+};
+
+use std::sync::Arc;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 
 #[derive(Debug)]
 pub struct Scene {
     objects: Vec<Id>,
     materials: Vec<Option<Material>>,
-    shapes: Vec<Option<Shape>>,
 
-    lights: Vec<Light>
+    /// Materials that replace the material of an object on some of the primitives it is
+    /// made of
+    leaf_materials: Vec<HashMap<usize, Material>>,
+
+    shapes: Vec<Option<Arc<dyn Shape>>>,
+    visibility: Vec<Visibility>,
+
+    /// The origins of the objects' own coordinates, used by solid textures
+    origins: Vec<Vector3>,
+
+    /// The lights that emissive objects were added as
+    emitters: Vec<Option<LightId>>,
+
+    lights: Vec<Box<dyn Light>>,
+    light_links: Vec<LightLinks>,
+
+    /// The power of each light, which makes it more likely to be sampled
+    light_powers: Vec<f64>,
+
+    background: Background,
+    integrator: Integrator
 }
 
 const MAX_BOUNCES: usize = 10;
 const MAX_LIGHT_SAMPLES: usize = 40;
 const MAX_BOUNCE_SAMPLES: usize = 10;
 
+/// The light reaching every surface from its surroundings, on top of the light from lights
+const AMBIENT: f64 = 0.1;
+
+/// The most times light scatters inside an object before it is given up on
+const MAX_SUBSURFACE_STEPS: usize = 256;
+
+/// Which kinds of rays see an object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Visibility {
+    pub camera: bool,
+
+    /// Whether the object blocks light from reaching other objects
+    pub shadow: bool,
+
+    pub reflection: bool
+}
+
+/// Which objects a light illuminates
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LightLinks {
+    #[default]
+    All,
+
+    /// Only these objects
+    Include(Vec<Id>),
+
+    /// Every object except these
+    Exclude(Vec<Id>)
+}
+
+/// How the light reflected by objects is found
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Integrator {
+    /// Follow rays bouncing between objects, which finds every reflection but needs many
+    /// samples to converge
+    #[default]
+    PathTracing,
+
+    /// Only light arriving straight from the lights, with highlights for glossy objects,
+    /// for fast previews. Objects do not reflect each other.
+    DirectLighting
+}
+
+/// A change to the scene that refers to something the scene does not have
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SceneError {
+    UnknownObject(Id),
+    UnknownLight(LightId),
+
+    /// The object is made of fewer primitives than the leaf refers to
+    UnknownLeaf {
+        object: Id,
+        leaf: usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RayKind {
+    Camera,
+    Shadow,
+    Reflection
+}
+
 struct RayProperties {
+    kind: RayKind,
     bounces: usize,
     light_samples: usize,
     bounce_samples: usize
 }
 
+impl RayProperties {
+    /// The properties of rays bouncing off a surface hit by this ray
+    fn bounce(&self) -> RayProperties {
+        RayProperties {
+            kind: RayKind::Reflection,
+            bounces: self.bounces - 1,
+            light_samples: (self.light_samples as f64 / 4.0).ceil() as usize,
+            bounce_samples: (self.bounce_samples as f64 / 5.0).ceil() as usize,
+        }
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Visibility {
+        Visibility {
+            camera: true,
+            shadow: true,
+            reflection: true,
+        }
+    }
+}
+
+impl Visibility {
+    /// Visible to every kind of ray except camera rays
+    pub fn hidden_from_camera() -> Visibility {
+        Visibility {
+            camera: false,
+            ..Visibility::default()
+        }
+    }
+
+    /// Visible to every kind of ray except shadow rays
+    pub fn no_shadows() -> Visibility {
+        Visibility {
+            shadow: false,
+            ..Visibility::default()
+        }
+    }
+
+    fn is_visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Shadow => self.shadow,
+            RayKind::Reflection => self.reflection,
+        }
+    }
+}
+
+
+impl LightLinks {
+    pub fn illuminates(&self, object: Id) -> bool {
+        match *self {
+            LightLinks::All => true,
+            LightLinks::Include(ref objects) => objects.contains(&object),
+            LightLinks::Exclude(ref objects) => !objects.contains(&object),
+        }
+    }
+}
+
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SceneError::UnknownObject(object) => write!(f, "there is no object {}", object),
+            SceneError::UnknownLight(light) => write!(f, "there is no light {}", light),
+            SceneError::UnknownLeaf { object, leaf } => {
+                write!(f, "object {} has no leaf {}", object, leaf)
+            },
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene::new()
+    }
+}
+
 impl Scene {
     pub fn new() -> Scene {
         Scene {
             objects: Vec::new(),
             materials: Vec::new(),
+            leaf_materials: Vec::new(),
             shapes: Vec::new(),
+            visibility: Vec::new(),
+            origins: Vec::new(),
+            emitters: Vec::new(),
 
             lights: Vec::new(),
+            light_links: Vec::new(),
+            light_powers: Vec::new(),
+
+            background: Background::default(),
+            integrator: Integrator::default(),
         }
     }
 
-    pub fn add_object(&mut self, shape: Shape, material: Material) -> Id {
+    /// Add an object to the scene. Emissive objects whose surface can be sampled, which are
+    /// those whose shape has a `surface_area`, also become lights, found with `emitter`. These
+    /// include composite shapes made only of spheres. Other emissive objects glow where they
+    /// are seen but do not light other objects, such as planes, composite shapes containing
+    /// planes and objects mixing materials that emit differently.
+    pub fn add_object<S: Shape + 'static>(&mut self, shape: S, material: Material) -> Id {
         let id = self.generate_next_id();
+        let shape: Arc<dyn Shape> = Arc::new(shape);
+
+        if let Some(color) = material.uniform_emission() {
+            if material.is_emissive() && shape.surface_area().is_some() {
+                let light = self.add_light(ShapeLight {
+                    shape: shape.clone(),
+                    color,
+                });
+
+                self.emitters[id] = Some(light);
+            }
+        }
 
+        self.origins[id] = shape.bounds().center().unwrap_or_else(Vector3::zero);
         self.materials[id] = Some(material);
         self.shapes[id] = Some(shape);
 
         id
     }
 
-    pub fn add_light(&mut self, light: Light) {
-        self.lights.push(light);
+    /// The light an emissive object was added as, for example to link it to other objects
+    pub fn emitter(&self, object: Id) -> Option<LightId> {
+        self.emitters.get(object).and_then(|light| *light)
+    }
+
+    pub fn add_light<L: Light + 'static>(&mut self, light: L) -> LightId {
+        let power = light.power();
+
+        self.lights.push(Box::new(light));
+        self.light_links.push(LightLinks::default());
+        self.light_powers.push(if power.is_finite() && power > 0.0 { power } else { 0.0 });
+
+        self.lights.len() - 1
+    }
+
+    /// Give the surface of one of the primitives that make up a composite object its own
+    /// material. The leaves are counted from the left, so in `difference(apple, bite)` the
+    /// surface cut out by the bite is leaf 1.
+    ///
+    /// Leaf materials that are emissive glow where they are seen, but do not light other
+    /// objects, as only the material an object is added with can make it a light.
+    pub fn set_leaf_material(
+        &mut self,
+        object: Id,
+        leaf: usize,
+        material: Material
+    ) -> Result<(), SceneError> {
+        let leaf_count = match self.shapes.get(object) {
+            Some(Some(shape)) => shape.leaf_count(),
+            _ => return Err(SceneError::UnknownObject(object)),
+        };
+
+        if leaf >= leaf_count {
+            return Err(SceneError::UnknownLeaf { object, leaf });
+        }
+
+        self.leaf_materials[object].insert(leaf, material);
+
+        Ok(())
+    }
+
+    /// Choose which kinds of rays see an object
+    pub fn set_visibility(
+        &mut self,
+        object: Id,
+        visibility: Visibility
+    ) -> Result<(), SceneError> {
+        match self.visibility.get_mut(object) {
+            Some(current) => {
+                *current = visibility;
+                Ok(())
+            },
+            None => Err(SceneError::UnknownObject(object)),
+        }
+    }
+
+    /// Choose which objects a light illuminates
+    pub fn set_light_links(
+        &mut self,
+        light: LightId,
+        links: LightLinks
+    ) -> Result<(), SceneError> {
+        match self.light_links.get_mut(light) {
+            Some(current) => {
+                *current = links;
+                Ok(())
+            },
+            None => Err(SceneError::UnknownLight(light)),
+        }
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
     }
 
-    pub fn trace(&self, ray: Ray) -> Color {
+    /// Get the light travelling backwards along a ray from the camera, and how much of the
+    /// image it covers
+    pub(crate) fn trace(&self, ray: Ray) -> Sample {
         let properties = RayProperties {
+            kind: RayKind::Camera,
             bounces: MAX_BOUNCES,
             light_samples: MAX_LIGHT_SAMPLES,
             bounce_samples: MAX_BOUNCE_SAMPLES,
         };
 
-        if let Some(color) = self.trace_ray_color(&ray, properties) {
-            color
-        } else {
-            Color {
-                r: 0.25 * ray.direction.x.abs(),
-                g: 0.25 * ray.direction.y.abs(),
-                b: 0.25 * ray.direction.z.abs()
-            }
+        match (self.get_intersection(&ray, RayKind::Camera), self.get_light_intersection(&ray)) {
+            (None, None) if self.background.is_transparent() => return Sample::transparent(),
+            (Some((entry, object)), light) => {
+                let in_front_of_light = light.is_none_or(|(distance, _)| entry.distance <= distance);
+
+                if let Some(material) = self.material(&entry, object) {
+                    if let (Some(matte), true) = (material.matte, in_front_of_light) {
+                        return self.trace_matte(&ray, entry, object, material, matte, properties);
+                    }
+                }
+            },
+            _ => {},
         }
+
+        let color = self.trace_ray_color(&ray, properties).unwrap_or_else(Color::black);
+
+        Sample::opaque(color)
     }
 }
 
@@ -83,7 +380,11 @@ impl Scene {
     fn resize_to_fit(&mut self, id: Id) {
         self.objects.push(id);
         self.materials.push(None);
+        self.leaf_materials.push(HashMap::new());
         self.shapes.push(None);
+        self.visibility.push(Visibility::default());
+        self.origins.push(Vector3::zero());
+        self.emitters.push(None);
     }
 
 
@@ -92,30 +393,102 @@ impl Scene {
             return None;
         }
 
-        if let Some((entry, object)) = self.get_intersection(&ray) {
-            if let Some(ref material) = self.materials[object] {
-                let ambient_color = material.color.apply_brightness(0.1);
-
-                let point = entry.point - ray.direction * 0.0001;
-                let adjusted_entry = Intersection {point, ..entry};
-
-                let light_color = self.light_color(adjusted_entry.clone(), properties.light_samples)
-                    .multiply(material.color);
+        let intersection = self.get_intersection(ray, properties.kind);
 
-                let bounce_color = self.bounce_color(ray, adjusted_entry, properties, material);
+        if let Some((distance, color)) = self.get_light_intersection(ray) {
+            let hidden = match intersection {
+                Some((ref entry, _)) => entry.distance < distance,
+                None => false,
+            };
 
-                return Some(ambient_color.add(light_color).add(bounce_color));
+            if !hidden {
+                return Some(color);
             }
         }
 
-        None
+        let (entry, object) = match intersection {
+            Some(intersection) => intersection,
+            None => return Some(self.escaped_color(ray, properties.kind)),
+        };
+
+        let material = self.material(&entry, object)?;
+
+        let surface_point = self.surface_point(&entry, object);
+        let shading = material.shading(&surface_point);
+
+        let view = -ray.direction;
+
+        // Light reflected like a mirror, or by the clear coat, is not left to be diffusely
+        // reflected
+        let cos_view = Vector3::dot(view, shading.normal);
+        let diffuse_fraction = shading.fresnel.diffuse_fraction(cos_view) *
+            (1.0 - shading.coat_reflectance(cos_view));
+        let diffuse_color = shading.color.apply_brightness(diffuse_fraction);
+
+        // Subsurface scattering carries the diffuse light into the object, to leave it
+        // somewhere else
+        let (diffuse_color, subsurface_color) = match shading.subsurface {
+            Some(subsurface) => {
+                let color = self.subsurface_color(&entry, object, &shading, subsurface, &properties)
+                    .apply_brightness(diffuse_fraction);
+
+                (Color::black(), color)
+            },
+            None => (diffuse_color, Color::black()),
+        };
+
+        let ambient_color = diffuse_color.apply_brightness(AMBIENT);
+
+        let point = entry.point - ray.direction * 0.0001;
+        let adjusted_entry = Intersection {point, ..entry};
+
+        let highlights = self.integrator == Integrator::DirectLighting;
+        let light_color = self.light_color(
+            adjusted_entry.clone(),
+            object,
+            properties.light_samples,
+            |light| reflected_light(view, light, entry.normal, &shading, diffuse_color, highlights)
+        );
+
+        let bounce_color = match self.integrator {
+            Integrator::PathTracing => self.bounce_color(ray, adjusted_entry, properties, &shading),
+            Integrator::DirectLighting => Color::black(),
+        };
+
+        let emitted = material.emitted(&surface_point);
+
+        Some(emitted + ambient_color + light_color + bounce_color + subsurface_color)
+    }
+
+    /// The material of the surface an intersection lies on
+    fn material(&self, entry: &Intersection, object: Id) -> Option<&Material> {
+        self.leaf_materials[object].get(&entry.leaf).or(self.materials[object].as_ref())
     }
 
-    fn get_intersection(&self, ray: &Ray) -> Option<(Intersection, Id)> {
+    fn surface_point(&self, entry: &Intersection, object: Id) -> SurfacePoint {
+        SurfacePoint {
+            position: entry.point,
+            object_position: entry.point - self.origins[object],
+            normal: entry.normal,
+            uv: entry.uv,
+            tangent: entry.tangent,
+            bitangent: entry.bitangent,
+        }
+    }
+
+    fn get_intersection(&self, ray: &Ray, kind: RayKind) -> Option<(Intersection, Id)> {
         let mut intersections = Vec::new();
 
         for &object in self.objects.iter() {
+            if !self.visibility[object].is_visible_to(kind) {
+                continue;
+            }
+
             if let Some(ref shape) = self.shapes[object] {
+                if !shape.bounds().hit(ray) {
+                    continue;
+                }
+
                 if let Some((entry, _)) = shape.first_intersection(ray) {
                     if entry.distance > 0.0 {
                         intersections.push((entry, object));
@@ -130,25 +503,120 @@ impl Scene {
             })
     }
 
-    fn light_color(&self, entry: Intersection, samples: usize) -> Color {
+    /// Find the closest light whose surface is hit by the ray, not counting lights infinitely
+    /// far away
+    fn get_light_intersection(&self, ray: &Ray) -> Option<(f64, Color)> {
+        self.lights.iter()
+            .filter_map(|light| light.intersect(ray))
+            .filter(|(distance, _)| *distance > 0.0 && distance.is_finite())
+            .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap())
+    }
+
+    /// Test if a ray misses every object and every light that is not infinitely far away
+    fn escapes(&self, ray: &Ray, kind: RayKind) -> bool {
+        self.get_intersection(ray, kind).is_none() && self.get_light_intersection(ray).is_none()
+    }
+
+    /// The light arriving along a ray that escapes the scene
+    fn escaped_color(&self, ray: &Ray, kind: RayKind) -> Color {
+        let background = if kind == RayKind::Camera || self.background.is_reflected() {
+            self.background.color(ray.direction)
+        } else {
+            Color::black()
+        };
+
+        self.lights.iter()
+            .filter_map(|light| light.intersect(ray))
+            .filter(|(distance, _)| distance.is_infinite())
+            .fold(background, |sum, (_, color)| sum + color)
+    }
+
+    fn light_color<F>(&self, entry: Intersection, object: Id, samples: usize, reflect: F) -> Color
+        where F: Fn(Vector3) -> Color {
+        let (color, _) = self.light_and_shadow(entry, object, samples, reflect);
+        color
+    }
+
+    /// Get the light reflected by the surface of an object, and the light it would reflect if
+    /// nothing cast shadows on it. The fraction reflected of the light arriving from each
+    /// direction is given by `reflect`.
+    ///
+    /// The lights linked to the object are sampled `samples` times each on average, choosing
+    /// brighter lights more often.
+    fn light_and_shadow<F>(
+        &self,
+        entry: Intersection,
+        object: Id,
+        samples: usize,
+        reflect: F
+    ) -> (Color, Color)
+        where F: Fn(Vector3) -> Color {
         let mut color = Color::black();
+        let mut unshadowed_color = Color::black();
 
-        for light in self.lights.iter() {
-            for _ in 0..samples {
-                if let Some(distance) = self.distance_to_light(entry.point, light) {
+        let linked = (0..self.lights.len())
+            .filter(|&light| self.light_links[light].illuminates(object))
+            .collect::<Vec<_>>();
 
-                    let diffuse = Vector3::dot(entry.point - light.sample_point(), -entry.normal);
+        let total_samples = samples * linked.len();
 
-                    let brightness = light.brightness(distance) * if diffuse > 0.0 {diffuse} else {0.0};
+        for _ in 0..total_samples {
+            let (light, chance) = match self.choose_light(&linked) {
+                Some(choice) => choice,
+                None => continue,
+            };
+
+            if let Some(sample) = self.lights[light].sample(entry.point) {
+                if sample.pdf <= 0.0 {
+                    continue;
+                }
 
-                    let light_color = light.color().apply_brightness(brightness / samples as f64);
+                let brightness = 1.0 / (sample.pdf * chance * total_samples as f64);
+                let radiance = sample.radiance
+                    .multiply(reflect(sample.direction))
+                    .apply_brightness(brightness);
 
-                    color = color.add(light_color);
+                unshadowed_color = unshadowed_color + radiance;
+
+                if self.is_visible(entry.point, &sample) {
+                    color = color + radiance;
                 }
             }
         }
 
-        color
+        (color, unshadowed_color)
+    }
+
+    /// Choose one of the lights at random, in proportion to their power, and return it along
+    /// with the chance of choosing it. Half of the time the lights are chosen uniformly instead,
+    /// as the power of a light only roughly tells how much it lights a given point.
+    fn choose_light(&self, lights: &[LightId]) -> Option<(LightId, f64)> {
+        let total_power = lights.iter().map(|&light| self.light_powers[light]).sum::<f64>();
+
+        let chance = |light: LightId| {
+            let uniform = 1.0 / lights.len() as f64;
+
+            if total_power > 0.0 {
+                0.5 * self.light_powers[light] / total_power + 0.5 * uniform
+            } else {
+                uniform
+            }
+        };
+
+        let mut remaining = sampler::gen_range(0.0, 1.0);
+
+        for &light in lights {
+            let chance = chance(light);
+
+            if remaining < chance {
+                return Some((light, chance));
+            }
+
+            remaining -= chance;
+        }
+
+        // Rounding errors can leave a little of the range to the last light
+        lights.last().map(|&light| (light, chance(light)))
     }
 
     fn bounce_color(
@@ -156,48 +624,613 @@ impl Scene {
         ray: &Ray,
         entry: Intersection,
         properties: RayProperties,
-        material: &Material
+        shading: &Shading
     ) -> Color {
         let mut bounce_color = Color::black();
+        let weight = 1.0 / properties.bounce_samples as f64;
 
         for _ in 0..properties.bounce_samples {
-            let bounce_ray = Ray::scatter(ray, entry.clone(), material.roughness);
-
-            let bounce_properties = RayProperties {
-                bounces: properties.bounces - 1,
-                light_samples: (properties.light_samples as f64 / 4.0).ceil() as usize,
-                bounce_samples: (properties.bounce_samples as f64 / 5.0).ceil() as usize,
+            let (bounce_ray, reflectance) = match scatter(ray, &entry, shading) {
+                Some(bounce) => bounce,
+                None => continue,
             };
 
-            if let Some(color) = self.trace_ray_color(&bounce_ray, bounce_properties) {
-                bounce_color = bounce_color.add(
-                    color.apply_brightness(material.reflectiveness / properties.bounce_samples as f64)
-                );
+            if let Some(color) = self.trace_ray_color(&bounce_ray, properties.bounce()) {
+                let reflectance = reflectance.apply_brightness(weight);
+                bounce_color = bounce_color + color.multiply(reflectance);
             }
         }
 
         bounce_color
     }
 
-    fn distance_to_light(&self, point: Vector3, light: &Light) -> Option<f64> {
-        let delta = light.sample_point() - point;
+    /// Get the light that enters an object where a ray hits it, and leaves the object again
+    /// after scattering around inside it
+    fn subsurface_color(
+        &self,
+        entry: &Intersection,
+        object: Id,
+        shading: &Shading,
+        subsurface: Subsurface,
+        properties: &RayProperties
+    ) -> Color {
+        let shape = match self.shapes[object] {
+            Some(ref shape) => shape,
+            None => return Color::black(),
+        };
+
+        let albedo = Subsurface::single_scattering_albedo(shading.color);
+
+        let walks = properties.bounce_samples.max(1);
+        let light_samples = (properties.light_samples / walks).max(1);
+
+        let mut color = Color::black();
+
+        for _ in 0..walks {
+            let (exit, throughput) = match random_walk(&**shape, entry, albedo, subsurface) {
+                Some(walk) => walk,
+                None => continue,
+            };
+
+            // The light leaves the surface as from a diffuse surface
+            let normal = exit.normal;
+            let light_color = self.light_color(exit, object, light_samples, |light| {
+                let incoming = Vector3::dot(light, normal).max(0.0);
+                Color::new(incoming, incoming, incoming)
+            });
+
+            let ambient_color = Color::new(AMBIENT, AMBIENT, AMBIENT);
+            let walk_color = throughput.multiply(light_color + ambient_color);
+
+            color = color + walk_color.apply_brightness(1.0 / walks as f64);
+        }
+
+        color
+    }
+
+    /// Get what the camera sees of a matte, in front of the background
+    fn trace_matte(
+        &self,
+        ray: &Ray,
+        entry: Intersection,
+        object: Id,
+        material: &Material,
+        matte: Matte,
+        properties: RayProperties
+    ) -> Sample {
+        let sample = match matte {
+            Matte::Holdout => return Sample::transparent(),
+            Matte::ShadowCatcher => self.catch_shadows(ray, entry, object, material, properties),
+        };
+
+        if self.background.is_transparent() {
+            sample
+        } else {
+            // The matte itself is invisible, so the background shows through it
+            let background = self.escaped_color(ray, RayKind::Camera)
+                .apply_brightness(1.0 - sample.alpha);
+            Sample::opaque(sample.color + background)
+        }
+    }
+
+    /// Get the shadows and reflections falling on a shadow catcher. The shadows are black and
+    /// as opaque as the fraction of the light they block, with the reflections on top of them.
+    /// Subsurface scattering is ignored, so shadows are caught as on an opaque surface.
+    fn catch_shadows(
+        &self,
+        ray: &Ray,
+        entry: Intersection,
+        object: Id,
+        material: &Material,
+        properties: RayProperties
+    ) -> Sample {
+        let shading = material.shading(&self.surface_point(&entry, object));
+
+        let point = entry.point - ray.direction * 0.0001;
+        let adjusted_entry = Intersection {point, ..entry};
 
+        let (light_color, unshadowed_color) = self.light_and_shadow(
+            adjusted_entry.clone(),
+            object,
+            properties.light_samples,
+            |light| {
+                reflected_light(-ray.direction, light, entry.normal, &shading, Color::white(), false)
+            }
+        );
+
+        let shadow = if unshadowed_color.luminance() > 0.0 {
+            (1.0 - light_color.luminance() / unshadowed_color.luminance()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        // Only reflections of objects and lights are caught, not of the background
+        let mut reflection_color = Color::black();
+        let mut reflection_alpha = 0.0;
+
+        let weight = 1.0 / properties.bounce_samples as f64;
+
+        for _ in 0..properties.bounce_samples {
+            let (bounce_ray, reflectance) = match scatter(ray, &adjusted_entry, &shading) {
+                Some(bounce) => bounce,
+                None => continue,
+            };
+
+            if self.escapes(&bounce_ray, RayKind::Reflection) {
+                continue;
+            }
+
+            if let Some(color) = self.trace_ray_color(&bounce_ray, properties.bounce()) {
+                let reflectance = reflectance.apply_brightness(weight);
+
+                reflection_color = reflection_color + color.multiply(reflectance);
+                reflection_alpha += reflectance.luminance();
+            }
+        }
+
+        let reflection_alpha = reflection_alpha.clamp(0.0, 1.0);
+
+        Sample {
+            color: reflection_color,
+            alpha: reflection_alpha + shadow * (1.0 - reflection_alpha),
+        }
+    }
+
+    /// Test if nothing blocks the light from reaching the point
+    fn is_visible(&self, point: Vector3, sample: &LightSample) -> bool {
         let light_ray = Ray {
             origin: point,
-            direction: delta.normal(),
+            direction: sample.direction,
         };
 
-        let light_depth = delta.length();
+        // Allow for rounding errors when the light is the surface of an object
+        if let Some((entry, _)) = self.get_intersection(&light_ray, RayKind::Shadow) {
+            sample.distance < entry.distance + 1.0e-6 * sample.distance
+        } else {
+            true
+        }
+    }
+}
+
+
+/// Scatter a ray off a surface, and get the fraction of the light arriving along it that is
+/// reflected. The clear coat is chosen as often as it reflects light from the view.
+fn scatter(ray: &Ray, entry: &Intersection, shading: &Shading) -> Option<(Ray, Color)> {
+    let view = -ray.direction;
+    let coat_probability = shading.coat_reflectance(Vector3::dot(view, shading.normal));
+
+    match shading.coat {
+        Some(coat) if sampler::gen_range(0.0, 1.0) < coat_probability => {
+            let (bounce, weight) = scatter_lobe(ray, entry, shading.normal, coat.roughness)?;
+            let half = (view + bounce.direction).normal();
 
-        if let Some((entry, _)) = self.get_intersection(&light_ray) {
-            if light_depth < entry.distance {
-                Some(light_depth)
+            let reflectance = shading.coat_reflectance(Vector3::dot(view, half)) / coat_probability;
+            Some((bounce, Color::white().apply_brightness(weight * reflectance)))
+        },
+        _ => {
+            // The light reflected by the coat is left out by choosing the coat instead
+            let (bounce, weight) = scatter_lobe(ray, entry, shading.normal, shading.roughness)?;
+            let half = (view + bounce.direction).normal();
+
+            let reflectance = shading.fresnel.reflectance(Vector3::dot(view, half));
+            Some((bounce, reflectance.apply_brightness(weight)))
+        },
+    }
+}
+
+/// Scatter a ray off a surface around its shading normal. Rays that leave above the shading
+/// normal but below the actual surface are mirrored back above it.
+fn scatter_lobe(
+    ray: &Ray,
+    entry: &Intersection,
+    normal: Vector3,
+    roughness: f64
+) -> Option<(Ray, f64)> {
+    let shading_entry = Intersection {
+        normal,
+        ..entry.clone()
+    };
+
+    let (mut bounce, weight) = Ray::scatter(ray, shading_entry, roughness)?;
+
+    let below = Vector3::dot(bounce.direction, entry.normal);
+    if below < 0.0 && Vector3::dot(bounce.direction, normal) >= 0.0 {
+        bounce.direction = (bounce.direction - 2.0 * below * entry.normal).normal();
+    }
+
+    Some((bounce, weight))
+}
+
+/// The fraction of the light arriving from a direction that is reflected towards the viewer,
+/// both diffusely and, if enabled, as a highlight
+fn reflected_light(
+    view: Vector3,
+    light: Vector3,
+    surface_normal: Vector3,
+    shading: &Shading,
+    diffuse_color: Color,
+    highlights: bool
+) -> Color {
+    let incoming = Vector3::dot(light, shading.normal).max(0.0) *
+        bump_shadowing(surface_normal, shading.normal, light);
+
+    let mut color = diffuse_color.apply_brightness(incoming);
+
+    if highlights {
+        let half = (view + light).normal();
+        let cos_half = Vector3::dot(shading.normal, half);
+
+        let highlight = BlinnPhong::from_roughness(shading.roughness).highlight(cos_half);
+        let reflectance = shading.fresnel.reflectance(Vector3::dot(view, half))
+            .apply_brightness(1.0 - shading.coat_reflectance(Vector3::dot(view, shading.normal)));
+
+        color = color + reflectance.apply_brightness(highlight * incoming);
+
+        if let Some(coat) = shading.coat {
+            let highlight = BlinnPhong::from_roughness(coat.roughness).highlight(cos_half);
+            let reflectance = shading.coat_reflectance(Vector3::dot(view, half));
+
+            color = color + Color::white().apply_brightness(reflectance * highlight * incoming);
+        }
+    }
+
+    color
+}
+
+/// Follow light from where it enters a shape until it leaves it, scattering in a random
+/// direction every time it hits a particle. Return where it leaves, facing out of the shape, and
+/// the fraction of the light that is left.
+// https://www.pbr-book.org/3ed-2018/Light_Transport_II_Volume_Rendering/Sampling_Volume_Scattering
+fn random_walk(
+    shape: &dyn Shape,
+    entry: &Intersection,
+    albedo: Color,
+    subsurface: Subsurface
+) -> Option<(Intersection, Color)> {
+    let mut ray = Ray {
+        origin: entry.point - entry.normal * 0.0001,
+        direction: sampler::cosine_hemisphere(-entry.normal),
+    };
+
+    let path = subsurface.mean_free_path;
+    let extinction = [1.0 / path.r, 1.0 / path.g, 1.0 / path.b];
+    let albedo = [albedo.r, albedo.g, albedo.b];
+
+    let mut throughput = [1.0; 3];
+
+    // The fraction of light of each channel left after travelling a distance
+    let transmittance = |distance: f64| {
+        [
+            (-extinction[0] * distance).exp(),
+            (-extinction[1] * distance).exp(),
+            (-extinction[2] * distance).exp()
+        ]
+    };
+
+    for _ in 0..MAX_SUBSURFACE_STEPS {
+        // Each distance is sampled for one channel, and weighted by the probability of
+        // sampling it for any of them. Channels are chosen in proportion to the light left
+        // in them, which keeps the weights from growing over many steps.
+        let total = throughput[0] + throughput[1] + throughput[2];
+        if total <= 0.0 {
+            return None;
+        }
+
+        let chances = [throughput[0] / total, throughput[1] / total, throughput[2] / total];
+
+        let choice = sampler::gen_range(0.0, 1.0);
+        let channel = if choice < chances[0] {
+            0
+        } else if choice < chances[0] + chances[1] {
+            1
+        } else {
+            2
+        };
+
+        let distance = -(1.0 - sampler::gen_range(0.0, 1.0)).ln() / extinction[channel];
+
+        let boundary = shape.all_intersections(&ray).into_iter()
+            .flat_map(|(entry, exit)| vec![entry, exit])
+            .filter(|intersection| intersection.distance > 0.0)
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())?;
+
+        if boundary.distance < distance {
+            let normal = if Vector3::dot(boundary.normal, ray.direction) > 0.0 {
+                boundary.normal
             } else {
-                None
+                -boundary.normal
+            };
+
+            let exit = Intersection {
+                point: boundary.point + normal * 0.0001,
+                normal,
+                ..boundary
+            };
+
+            // The probability of travelling past the boundary
+            let passed = transmittance(boundary.distance);
+            let probability = (0..3).map(|c| chances[c] * passed[c]).sum::<f64>();
+
+            for c in 0..3 {
+                throughput[c] *= passed[c] / probability;
             }
-        } else {
-            Some(light_depth)
+
+            return Some((exit, Color::new(throughput[0], throughput[1], throughput[2])));
+        }
+
+        let passed = transmittance(distance);
+        let pdf = (0..3).map(|c| chances[c] * extinction[c] * passed[c]).sum::<f64>();
+
+        for c in 0..3 {
+            throughput[c] *= albedo[c] * extinction[c] * passed[c] / pdf;
         }
+
+        ray = Ray {
+            origin: ray.origin + ray.direction * distance,
+            direction: sampler::sphere(),
+        };
     }
+
+    None
+}
+
+/// Smoothly fade out the light reaching a shading normal that leans away from the surface
+/// normal, which otherwise leaves hard shadow terminators. Light from behind the surface does
+/// not reach it at all.
+// Chiang, Li and Burley, "Taming the Shadow Terminator", 2019
+fn bump_shadowing(surface_normal: Vector3, shading_normal: Vector3, light: Vector3) -> f64 {
+    let cos_surface = Vector3::dot(surface_normal, light);
+    let cos_shading = Vector3::dot(shading_normal, light);
+    let cos_normals = Vector3::dot(surface_normal, shading_normal);
+
+    if cos_surface <= 0.0 {
+        return 0.0;
+    }
+
+    if cos_shading <= 0.0 || cos_normals <= 0.0 {
+        return 1.0;
+    }
+
+    let g = (cos_surface / (cos_shading * cos_normals)).min(1.0);
+
+    -g * g * g + g * g + g
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shape::{
+        Sphere,
+        Plane,
+        intersection,
+        difference
+    };
+    use light::{
+        PointLight,
+        Power,
+        Falloff
+    };
+
+    use std::f64::consts::PI;
+
+    fn point_light(x: f64, strength: f64) -> PointLight {
+        PointLight {
+            point: Vector3::new(x, 1.0, 0.0),
+            color: Color::new(1.0, 1.0, 1.0),
+            size: 0.0,
+            power: Power::Strength(strength),
+            falloff: Falloff::InverseSquare,
+        }
+    }
+
+    fn surface_at_origin() -> Intersection {
+        Intersection {
+            point: Vector3::zero(),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            distance: 1.0,
+            uv: (0.0, 0.0),
+            tangent: Vector3::new(1.0, 0.0, 0.0),
+            bitangent: Vector3::new(0.0, 0.0, 1.0),
+            leaf: 0,
+        }
+    }
+
+    #[test]
+    fn lights_chosen_by_power_give_the_light_of_every_light() {
+        sampler::seed_sample(0, 0, 0, 0);
+
+        let mut scene = Scene::new();
+        scene.add_light(point_light(-1.0, 100.0));
+        scene.add_light(point_light(0.0, 1.0));
+        scene.add_light(point_light(2.0, 10.0));
+
+        // A strength is the intensity of the light, which falls off with the squared distance
+        let expected = 100.0 / 2.0 + 1.0 / 1.0 + 10.0 / 5.0;
+
+        let count = 20_000;
+        let total = (0..count)
+            .map(|_| {
+                let white = |_| Color::new(1.0, 1.0, 1.0);
+                scene.light_color(surface_at_origin(), 0, 1, white).r
+            })
+            .sum::<f64>();
+
+        let estimate = total / count as f64;
+        assert!((estimate - expected).abs() < 0.02 * expected, "{} != {}", estimate, expected);
+    }
+
+    #[test]
+    fn emissive_composite_objects_light_their_neighbours() {
+        let glow = || Material::new(Color::black(), 1.0, 0.0)
+            .emissive(Color::new(1.0, 1.0, 1.0), 1.0);
+        let sphere = |x: f64, y: f64| Sphere {
+            center: Vector3::new(x, y, 0.0),
+            radius: 1.0,
+        };
+
+        let lambert = |direction: Vector3| {
+            Color::new(1.0, 1.0, 1.0).apply_brightness(direction.y.max(0.0) / PI)
+        };
+
+        let light_at_origin = |scene: &Scene| {
+            sampler::seed_sample(0, 0, 0, 2);
+
+            let count = 5000;
+            let total = (0..count)
+                .map(|_| scene.light_color(surface_at_origin(), 0, 10, lambert).r)
+                .sum::<f64>();
+
+            total / count as f64
+        };
+
+        let mut lone_sphere = Scene::new();
+        lone_sphere.add_object(sphere(0.0, 3.0), glow());
+
+        // Cutting away nothing of a sphere leaves the same light
+        let mut uncut = Scene::new();
+        let light = uncut.add_object(difference(sphere(0.0, 3.0), sphere(10.0, 3.0)), glow());
+
+        let mut cut = Scene::new();
+        cut.add_object(difference(sphere(0.0, 3.0), sphere(0.0, 1.5)), glow());
+
+        assert!(uncut.emitter(light).is_some());
+
+        // A sphere of radiance 1 covering a cone with a sine of 1/3 overhead
+        let expected = 1.0 / 9.0;
+
+        let sphere_light = light_at_origin(&lone_sphere);
+        let uncut_light = light_at_origin(&uncut);
+        let cut_light = light_at_origin(&cut);
+
+        assert!((sphere_light - expected).abs() < 0.03 * expected, "{}", sphere_light);
+        assert!((uncut_light - expected).abs() < 0.03 * expected, "{}", uncut_light);
+        assert!(cut_light > 0.0);
+    }
+
+    #[test]
+    fn leaves_of_nested_composites_use_their_own_materials() {
+        let sphere = |x: f64, z: f64, radius: f64| Sphere {
+            center: Vector3::new(x, 0.0, z),
+            radius,
+        };
+
+        // A lens between two spheres, leaves 0 and 1, with a bite taken out by leaf 2
+        let lens = intersection(sphere(0.0, 0.0, 2.0), sphere(1.0, 0.0, 2.0));
+        let shape = difference(lens, sphere(0.0, -2.0, 1.0));
+
+        let mut scene = Scene::new();
+        let object = scene.add_object(shape, Material::new(Color::black(), 1.0, 0.0));
+
+        // Each leaf gets a shade of gray that tells them apart
+        let shade = |leaf: usize| 0.25 * (leaf + 1) as f64;
+
+        for leaf in 0..3 {
+            let color = Color::new(shade(leaf), shade(leaf), shade(leaf));
+            scene.set_leaf_material(object, leaf, Material::new(color, 1.0, 0.0)).unwrap();
+        }
+
+        match scene.set_leaf_material(object, 3, Material::new(Color::black(), 1.0, 0.0)) {
+            Err(SceneError::UnknownLeaf { leaf: 3, .. }) => {},
+            result => panic!("leaf 3 was accepted: {:?}", result),
+        }
+
+        for &(x, leaf) in &[(1.5, 0), (-0.9, 1), (0.0, 2)] {
+            let ray = Ray {
+                origin: Vector3::new(x, 0.0, -10.0),
+                direction: Vector3::new(0.0, 0.0, 1.0),
+            };
+
+            let (entry, hit) = scene.get_intersection(&ray, RayKind::Camera).unwrap();
+            let material = scene.material(&entry, hit).unwrap();
+            let color = material.color.color(&scene.surface_point(&entry, hit));
+
+            assert_eq!(entry.leaf, leaf);
+            assert_eq!(color.r, shade(leaf));
+        }
+    }
+
+    #[test]
+    fn objects_hidden_from_shadows_cast_none() {
+        sampler::seed_sample(0, 0, 0, 3);
+
+        let mut scene = Scene::new();
+        scene.add_light(point_light(0.0, 1.0));
+
+        let blocker = scene.add_object(
+            Sphere { center: Vector3::new(0.0, 0.5, 0.0), radius: 0.25 },
+            Material::new(Color::new(1.0, 1.0, 1.0), 1.0, 0.0)
+        );
+
+        let white = |_| Color::new(1.0, 1.0, 1.0);
+        assert_eq!(scene.light_color(surface_at_origin(), 1, 1, white).r, 0.0);
+
+        scene.set_visibility(blocker, Visibility::no_shadows()).unwrap();
+        assert_eq!(scene.light_color(surface_at_origin(), 1, 1, white).r, 1.0);
+    }
+
+    #[test]
+    fn objects_hidden_from_the_camera_are_seen_in_reflections() {
+        sampler::seed_sample(0, 0, 0, 4);
+
+        let mut scene = Scene::new();
+        scene.set_background(Background::Color(Color::black()));
+
+        let mirror = Plane {
+            origin: Vector3::zero(),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+        };
+        scene.add_object(mirror, Material::new(Color::black(), 0.0, 1.0));
+
+        let glow = scene.add_object(
+            Sphere { center: Vector3::new(0.0, 2.0, 5.0), radius: 1.0 },
+            Material::new(Color::black(), 1.0, 0.0).emissive(Color::new(1.0, 1.0, 1.0), 1.0)
+        );
+        scene.set_visibility(glow, Visibility::hidden_from_camera()).unwrap();
+
+        let origin = Vector3::new(0.0, 2.0, -5.0);
+        let direct = Ray {
+            origin,
+            direction: Vector3::new(0.0, 0.0, 1.0),
+        };
+        let reflected = Ray {
+            origin,
+            direction: Vector3::new(0.0, -2.0, 5.0).normal(),
+        };
+
+        assert_eq!(scene.trace(direct).color.r, 0.0);
+        assert!(scene.trace(reflected).color.r > 0.5);
+    }
+
+    #[test]
+    fn lights_do_not_illuminate_excluded_objects() {
+        sampler::seed_sample(0, 0, 0, 5);
+
+        let mut scene = Scene::new();
+        let light = scene.add_light(point_light(0.0, 1.0));
+        scene.set_light_links(light, LightLinks::Exclude(vec![7])).unwrap();
+
+        let white = |_| Color::new(1.0, 1.0, 1.0);
+        assert_eq!(scene.light_color(surface_at_origin(), 7, 1, white).r, 0.0);
+        assert_eq!(scene.light_color(surface_at_origin(), 3, 1, white).r, 1.0);
+
+        assert!(scene.set_light_links(light + 1, LightLinks::All).is_err());
+    }
+
+    #[test]
+    fn brighter_lights_are_chosen_more_often() {
+        sampler::seed_sample(0, 0, 0, 1);
+
+        let mut scene = Scene::new();
+        let dim = scene.add_light(point_light(0.0, 1.0));
+        let bright = scene.add_light(point_light(0.0, 9.0));
+
+        let (_, dim_chance) = (0..100)
+            .filter_map(|_| scene.choose_light(&[dim, bright]))
+            .find(|&(light, _)| light == dim)
+            .unwrap();
+
+        // Half uniformly and half by power
+        assert!((dim_chance - (0.25 + 0.05)).abs() < 1.0e-9);
+    }
+}
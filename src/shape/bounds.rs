@@ -0,0 +1,89 @@
+use vector::Vector3;
+use ray::Ray;
+
+/// An axis-aligned box
+#[derive(Debug, Copy, Clone)]
+pub struct Bounds {
+    pub min: Vector3,
+    pub max: Vector3
+}
+
+
+impl Bounds {
+    pub fn new(min: Vector3, max: Vector3) -> Bounds {
+        Bounds {
+            min,
+            max
+        }
+    }
+
+    /// A box that contains everything
+    pub fn infinite() -> Bounds {
+        let infinity = Vector3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+
+        Bounds {
+            min: -infinity,
+            max: infinity,
+        }
+    }
+
+    /// The point in the middle of the box, if it is finite
+    pub fn center(&self) -> Option<Vector3> {
+        let center = (self.min + self.max) / 2.0;
+
+        if center.x.is_finite() && center.y.is_finite() && center.z.is_finite() {
+            Some(center)
+        } else {
+            None
+        }
+    }
+
+    /// The smallest box containing both boxes
+    pub fn union(a: Bounds, b: Bounds) -> Bounds {
+        Bounds {
+            min: Vector3::new(a.min.x.min(b.min.x), a.min.y.min(b.min.y), a.min.z.min(b.min.z)),
+            max: Vector3::new(a.max.x.max(b.max.x), a.max.y.max(b.max.y), a.max.z.max(b.max.z)),
+        }
+    }
+
+    /// The box covered by both boxes
+    pub fn intersection(a: Bounds, b: Bounds) -> Bounds {
+        Bounds {
+            min: Vector3::new(a.min.x.max(b.min.x), a.min.y.max(b.min.y), a.min.z.max(b.min.z)),
+            max: Vector3::new(a.max.x.min(b.max.x), a.max.y.min(b.max.y), a.max.z.min(b.max.z)),
+        }
+    }
+
+    /// Test if a ray passes through the box in front of its origin
+    // https://www.scratchapixel.com/lessons/3d-basic-rendering/minimal-ray-tracer-rendering-simple-shapes/ray-box-intersection
+    pub fn hit(&self, ray: &Ray) -> bool {
+        let mut near = 0.0f64;
+        let mut far = f64::INFINITY;
+
+        let axes = [
+            (self.min.x, self.max.x, ray.origin.x, ray.direction.x),
+            (self.min.y, self.max.y, ray.origin.y, ray.direction.y),
+            (self.min.z, self.max.z, ray.origin.z, ray.direction.z),
+        ];
+
+        for &(min, max, origin, direction) in axes.iter() {
+            if min > max {
+                return false;
+            }
+
+            if direction == 0.0 {
+                if origin < min || origin > max {
+                    return false;
+                }
+            } else {
+                let a = (min - origin) / direction;
+                let b = (max - origin) / direction;
+
+                near = near.max(a.min(b));
+                far = far.min(a.max(b));
+            }
+        }
+
+        near <= far
+    }
+}
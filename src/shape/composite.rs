@@ -0,0 +1,410 @@
+use vector::Vector3;
+use sampler;
+use ray::{
+    self,
+    Ray,
+    EntryExit
+};
+
+use std::sync::OnceLock;
+
+use super::{
+    Shape,
+    Bounds
+};
+
+/// The volume that is inside both `a` and `b`
+#[derive(Debug)]
+pub struct Intersection {
+    pub a: Box<dyn Shape>,
+    pub b: Box<dyn Shape>,
+
+    area: OnceLock<Option<f64>>
+}
+
+/// The volume of `a` that is not inside `b`
+#[derive(Debug)]
+pub struct Difference {
+    pub a: Box<dyn Shape>,
+    pub b: Box<dyn Shape>,
+
+    area: OnceLock<Option<f64>>
+}
+
+/// The number of points on the surfaces of `a` and `b` used to estimate the area of a
+/// composite shape
+const AREA_SAMPLES: usize = 16384;
+
+/// The most points on the surfaces of `a` and `b` tried when sampling the surface of a
+/// composite shape
+const MAX_SAMPLE_ATTEMPTS: usize = 64;
+
+
+pub fn intersection<A, B>(a: A, b: B) -> Intersection
+    where A: Shape + 'static, B: Shape + 'static {
+    Intersection {
+        a: Box::new(a),
+        b: Box::new(b),
+        area: OnceLock::new(),
+    }
+}
+
+pub fn difference<A, B>(a: A, b: B) -> Difference
+    where A: Shape + 'static, B: Shape + 'static {
+    Difference {
+        a: Box::new(a),
+        b: Box::new(b),
+        area: OnceLock::new(),
+    }
+}
+
+
+impl Shape for Intersection {
+    fn all_intersections(&self, ray: &Ray) -> Vec<EntryExit> {
+        let regions = get_regions(ray, &*self.a, &*self.b);
+        regions.into_iter()
+            .filter(|region| region.a && region.b)
+            .map(|region| (region.start, region.end))
+            .collect()
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::intersection(self.a.bounds(), self.b.bounds())
+    }
+
+    fn contains(&self, point: Vector3) -> bool {
+        self.a.contains(point) && self.b.contains(point)
+    }
+
+    /// Estimated once, by sampling the surfaces of `a` and `b`
+    fn surface_area(&self) -> Option<f64> {
+        *self.area.get_or_init(|| {
+            estimate_area(&*self.a, &*self.b, |point, normal, on_a| self.keep(point, normal, on_a))
+        })
+    }
+
+    fn sample_surface(&self) -> Option<(Vector3, Vector3)> {
+        sample_surface(&*self.a, &*self.b, |point, normal, on_a| self.keep(point, normal, on_a))
+    }
+
+    /// The coordinates of whichever of `a` and `b` the point lies on
+    fn surface_coordinates(&self, point: Vector3) -> (f64, f64) {
+        leaf_coordinates(&*self.a, &*self.b, point)
+    }
+
+    fn leaf_count(&self) -> usize {
+        self.a.leaf_count() + self.b.leaf_count()
+    }
+}
+
+impl Shape for Difference {
+    fn all_intersections(&self, ray: &Ray) -> Vec<EntryExit> {
+        let regions = get_regions(ray, &*self.a, &*self.b);
+
+        regions.into_iter()
+            .filter(|region| region.a && !region.b)
+            .map(|region| (region.start, region.end))
+            .collect()
+    }
+
+    fn bounds(&self) -> Bounds {
+        self.a.bounds()
+    }
+
+    fn contains(&self, point: Vector3) -> bool {
+        self.a.contains(point) && !self.b.contains(point)
+    }
+
+    /// Estimated once, by sampling the surfaces of `a` and `b`
+    fn surface_area(&self) -> Option<f64> {
+        *self.area.get_or_init(|| {
+            estimate_area(&*self.a, &*self.b, |point, normal, on_a| self.keep(point, normal, on_a))
+        })
+    }
+
+    fn sample_surface(&self) -> Option<(Vector3, Vector3)> {
+        sample_surface(&*self.a, &*self.b, |point, normal, on_a| self.keep(point, normal, on_a))
+    }
+
+    /// The coordinates of whichever of `a` and `b` the point lies on
+    fn surface_coordinates(&self, point: Vector3) -> (f64, f64) {
+        leaf_coordinates(&*self.a, &*self.b, point)
+    }
+
+    fn leaf_count(&self) -> usize {
+        self.a.leaf_count() + self.b.leaf_count()
+    }
+}
+
+
+impl Intersection {
+    /// Get the normal of the shape at a point on the surface of `a` or `b`, if the point is on
+    /// the surface of the shape
+    fn keep(&self, point: Vector3, normal: Vector3, on_a: bool) -> Option<Vector3> {
+        let other = if on_a { &self.b } else { &self.a };
+
+        if other.contains(point) {
+            Some(normal)
+        } else {
+            None
+        }
+    }
+}
+
+impl Difference {
+    /// Get the normal of the shape at a point on the surface of `a` or `b`, if the point is on
+    /// the surface of the shape. Where `b` cuts into `a`, its normal is reversed.
+    fn keep(&self, point: Vector3, normal: Vector3, on_a: bool) -> Option<Vector3> {
+        if on_a && !self.b.contains(point) {
+            Some(normal)
+        } else if !on_a && self.a.contains(point) {
+            Some(-normal)
+        } else {
+            None
+        }
+    }
+}
+
+
+/// Get the surface coordinates of a point on the surface of `a` or `b`, from the shape it lies
+/// on. Points on both surfaces use the coordinates of `a`.
+fn leaf_coordinates(a: &dyn Shape, b: &dyn Shape, point: Vector3) -> (f64, f64) {
+    if is_on_surface(b, point) && !is_on_surface(a, point) {
+        b.surface_coordinates(point)
+    } else {
+        a.surface_coordinates(point)
+    }
+}
+
+/// Test if a point lies on the surface of a shape, by stepping a little along each axis to
+/// see if that crosses the surface
+fn is_on_surface(shape: &dyn Shape, point: Vector3) -> bool {
+    let step = 1.0e-6 * point.length().max(1.0);
+
+    let axes = [
+        Vector3::new(step, 0.0, 0.0),
+        Vector3::new(0.0, step, 0.0),
+        Vector3::new(0.0, 0.0, step),
+    ];
+
+    axes.iter().any(|&offset| shape.contains(point + offset) != shape.contains(point - offset))
+}
+
+/// Get a random point on the surface of either `a` or `b`, all points being equally likely,
+/// along with the normal there and whether the point is on `a`
+fn sample_either(a: &dyn Shape, b: &dyn Shape) -> Option<(Vector3, Vector3, bool)> {
+    let area_a = a.surface_area()?;
+    let area_b = b.surface_area()?;
+
+    if sampler::gen_range(0.0, area_a + area_b) < area_a {
+        a.sample_surface().map(|(point, normal)| (point, normal, true))
+    } else {
+        b.sample_surface().map(|(point, normal)| (point, normal, false))
+    }
+}
+
+/// Sample the surface of a composite shape by trying points on the surfaces of `a` and `b`
+/// until one is kept. Shapes that keep very little of `a` and `b` may give no point.
+fn sample_surface<F>(a: &dyn Shape, b: &dyn Shape, keep: F) -> Option<(Vector3, Vector3)>
+    where F: Fn(Vector3, Vector3, bool) -> Option<Vector3> {
+    for _ in 0..MAX_SAMPLE_ATTEMPTS {
+        let (point, normal, on_a) = sample_either(a, b)?;
+
+        if let Some(normal) = keep(point, normal, on_a) {
+            return Some((point, normal));
+        }
+    }
+
+    None
+}
+
+/// Estimate the area of a composite shape from the fraction of points on the surfaces of `a`
+/// and `b` that are kept. The same points are used every time, so the estimate does not
+/// change between renders.
+fn estimate_area<F>(a: &dyn Shape, b: &dyn Shape, keep: F) -> Option<f64>
+    where F: Fn(Vector3, Vector3, bool) -> Option<Vector3> {
+    let total = a.surface_area()? + b.surface_area()?;
+
+    sampler::with_seed(0, || {
+        let kept = (0..AREA_SAMPLES)
+            .filter_map(|_| sample_either(a, b))
+            .filter(|&(point, normal, on_a)| keep(point, normal, on_a).is_some())
+            .count();
+
+        Some(total * kept as f64 / AREA_SAMPLES as f64)
+    })
+}
+
+
+#[derive(Clone, Debug)]
+struct Region {
+    start: ray::Intersection,
+    end: ray::Intersection,
+    a: bool,
+    b: bool
+}
+
+fn get_regions(ray: &Ray, a: &dyn Shape, b: &dyn Shape) -> Vec<Region> {
+    enum Owner {A, B}
+
+    let a_intersections = a.all_intersections(ray);
+    let b_intersections = b.all_intersections(ray);
+
+    // The leaves of `b` are numbered after those of `a`
+    let b_leaves = a.leaf_count();
+    let b_intersections = b_intersections.into_iter().map(|(entry, exit)| {
+        (
+            ray::Intersection { leaf: entry.leaf + b_leaves, ..entry },
+            ray::Intersection { leaf: exit.leaf + b_leaves, ..exit }
+        )
+    });
+
+    let mut intersections = a_intersections.into_iter().flat_map(|(entry, exit)|{
+        vec![(entry, Owner::A), (exit.inverse(), Owner::A)]
+    }).chain(b_intersections.flat_map(|(entry, exit)|{
+        vec![(entry, Owner::B), (exit.inverse(), Owner::B)]
+    })).collect::<Vec<_>>();
+
+    intersections.sort_by(|a, b| a.0.distance.partial_cmp(&b.0.distance).unwrap());
+
+    let (tangent, bitangent) = ray.direction.perpendicular_axes();
+
+    let start = ray::Intersection {
+        point: ray.direction * -f64::INFINITY,
+        normal: -ray.direction,
+        distance: -f64::INFINITY,
+        uv: (0.0, 0.0),
+        tangent,
+        bitangent,
+        leaf: 0,
+    };
+
+    let mut regions = vec![
+        Region {
+            start: start.clone(),
+            end: start.clone(),
+            a: false,
+            b: false,
+        }
+    ];
+
+    for (i, (intersection, owner)) in intersections.into_iter().enumerate() {
+        let (previous_a, previous_b) = {
+            let previous_region = &mut regions[i];
+
+            previous_region.end = intersection.clone().inverse();
+
+            (previous_region.a, previous_region.b)
+        };
+
+        let (a, b) = match owner {
+            Owner::A => (!previous_a, previous_b),
+            Owner::B => (previous_a, !previous_b),
+        };
+
+        let region = Region {
+            start: intersection.clone(),
+            end: intersection,
+            a,
+            b,
+        };
+
+        regions.push(region);
+    }
+
+    if let Some(region) = regions.last_mut() {
+        region.end = ray::Intersection {
+            point: ray.direction * f64::INFINITY,
+            distance: f64::INFINITY,
+            ..start.inverse()
+        };
+    }
+
+    regions
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shape::Sphere;
+
+    use std::f64::consts::PI;
+
+    fn sphere(x: f64, y: f64, z: f64, radius: f64) -> Sphere {
+        Sphere {
+            center: Vector3::new(x, y, z),
+            radius,
+        }
+    }
+
+    #[test]
+    fn composite_area_is_the_area_of_its_kept_surfaces() {
+        // Each sphere has a cap of height 1/2 inside the other, with a third of the area of
+        // the rest of the sphere
+        let lens = intersection(sphere(0.0, 0.0, 0.0, 1.0), sphere(1.0, 0.0, 0.0, 1.0));
+        let bite = difference(sphere(0.0, 0.0, 0.0, 1.0), sphere(1.0, 0.0, 0.0, 1.0));
+
+        let lens_area = lens.surface_area().unwrap();
+        let bite_area = bite.surface_area().unwrap();
+
+        assert!((lens_area - 2.0 * PI).abs() < 0.03 * 2.0 * PI, "{}", lens_area);
+        assert!((bite_area - 4.0 * PI).abs() < 0.03 * 4.0 * PI, "{}", bite_area);
+    }
+
+    #[test]
+    fn sampled_points_lie_on_the_composite_surface() {
+        sampler::seed_sample(0, 0, 0, 0);
+
+        let shape = difference(sphere(0.0, 0.0, 0.0, 1.0), sphere(1.0, 0.0, 0.0, 1.0));
+
+        for _ in 0..1000 {
+            let (point, normal) = shape.sample_surface().unwrap();
+
+            // Just outside the surface is outside the shape, and just inside is inside
+            assert!(!shape.contains(point + 1.0e-6 * normal));
+            assert!(shape.contains(point - 1.0e-6 * normal));
+        }
+    }
+
+    #[test]
+    fn surface_coordinates_come_from_the_leaf_that_was_hit() {
+        let shape = difference(sphere(0.0, 0.0, 0.0, 3.0), sphere(0.0, 0.0, -3.0, 2.0));
+
+        // The first ray hits the cut face of the cutter, the second the outside of the ball
+        for &origin in &[Vector3::new(0.3, 0.1, -10.0), Vector3::new(2.5, 0.1, -10.0)] {
+            let ray = Ray {
+                origin,
+                direction: Vector3::new(0.0, 0.0, 1.0),
+            };
+
+            let (entry, _) = shape.first_intersection(&ray).unwrap();
+            let (u, v) = shape.surface_coordinates(entry.point);
+
+            assert!((u - entry.uv.0).abs() < 1.0e-9 && (v - entry.uv.1).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn cut_faces_keep_the_tangents_of_their_surface() {
+        let cutter = sphere(0.0, 0.0, -3.0, 2.0);
+        let shape = difference(sphere(0.0, 0.0, 0.0, 3.0), sphere(0.0, 0.0, -3.0, 2.0));
+
+        let ray = Ray {
+            origin: Vector3::new(0.3, 0.1, -10.0),
+            direction: Vector3::new(0.0, 0.0, 1.0),
+        };
+
+        let (entry, _) = shape.first_intersection(&ray).unwrap();
+        assert_eq!(entry.leaf, 1);
+
+        // Moving along the tangent and bitangent changes the coordinates by one unit each
+        let step = 1.0e-4;
+        for &(axis, u, v) in &[(entry.tangent, 1.0, 0.0), (entry.bitangent, 0.0, 1.0)] {
+            let moved = cutter.surface_coordinates(entry.point + step * axis);
+
+            assert!(((moved.0 - entry.uv.0) / step - u).abs() < 1.0e-2);
+            assert!(((moved.1 - entry.uv.1) / step - v).abs() < 1.0e-2);
+        }
+    }
+}
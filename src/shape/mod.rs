@@ -1,143 +1,76 @@
 mod sphere;
 mod plane;
+mod composite;
+mod bounds;
 
 pub use self::sphere::Sphere;
 pub use self::plane::Plane;
+pub use self::composite::{
+    Intersection,
+    Difference,
+    intersection,
+    difference
+};
+pub use self::bounds::Bounds;
 
+use vector::Vector3;
 use ray::{
     Ray,
-    EntryExit,
-    Intersection
+    EntryExit
 };
 
-#[derive(Debug, Clone)]
-pub enum Shape {
-    Sphere(Sphere),
-    Plane(Plane),
-
-    Intersection(Box<Shape>, Box<Shape>),
-    Difference(Box<Shape>, Box<Shape>),
-}
-
-
-impl Shape {
-    /// Return the first entry and exit pair of intersections
-    pub fn first_intersection(&self, ray: &Ray) -> Option<EntryExit> {
-        let intersections = match self {
-            Shape::Sphere(sphere) => sphere.intersection(ray),
-            Shape::Plane(plane) => plane.intersection(ray),
-
-            Shape::Intersection(a, b) => intersection(ray, a, b),
-            Shape::Difference(a, b) => difference(ray, a, b),
+use std::fmt::Debug;
+
+/// A solid that rays can intersect.
+///
+/// Shapes are closed: every entry into the shape along a ray is followed by an exit, which
+/// lets them be combined into composite objects.
+pub trait Shape: Debug + Send + Sync {
+    /// Return all entry and exit pairs of intersections, ordered by distance along the ray
+    fn all_intersections(&self, ray: &Ray) -> Vec<EntryExit>;
+
+    /// Return a box that contains the whole shape
+    fn bounds(&self) -> Bounds;
+
+    /// Map a point on the surface of the shape to two-dimensional surface coordinates, the
+    /// same as the `uv` of intersections at that point
+    fn surface_coordinates(&self, point: Vector3) -> (f64, f64);
+
+    /// Test if a point is inside the shape.
+    ///
+    /// By default, this tests if a ray from the point leaves the shape before it enters it,
+    /// which requires that intersections behind the origin of rays are returned.
+    fn contains(&self, point: Vector3) -> bool {
+        let ray = Ray {
+            origin: point,
+            direction: Vector3::new(0.0, 1.0, 0.0),
         };
 
-        if intersections.len() == 0 {
-            None
-        } else {
-            intersections.first().map(|a| a.clone())
-        }
+        self.all_intersections(&ray)
+            .iter()
+            .any(|(entry, exit)| entry.distance <= 0.0 && exit.distance > 0.0)
     }
 
-    pub fn all_intersections(&self, ray: &Ray) -> Vec<EntryExit> {
-        match self {
-            Shape::Sphere(sphere) => sphere.intersection(ray),
-            Shape::Plane(plane) => plane.intersection(ray),
-
-            Shape::Intersection(a, b) => intersection(ray, a, b),
-            Shape::Difference(a, b) => difference(ray, a, b),
-        }
+    /// The area of the surface, if points on it can be sampled. Only emissive objects whose
+    /// shape has an area light the scene.
+    fn surface_area(&self) -> Option<f64> {
+        None
     }
-}
-
-
-fn intersection(ray: &Ray, a: &Shape, b: &Shape) -> Vec<EntryExit> {
-    let regions = get_regions(ray, a, b);
-    regions.into_iter()
-        .filter(|region| region.a && region.b)
-        .map(|region| (region.start, region.end))
-        .collect()
-}
-
-fn difference(ray: &Ray, a: &Shape, b: &Shape) -> Vec<EntryExit> {
-    let regions = get_regions(ray, a, b);
-
-    regions.into_iter()
-        .filter(|region| region.a && !region.b)
-        .map(|region| (region.start, region.end))
-        .collect()
-}
-
-
-#[derive(Clone, Debug)]
-struct Region {
-    start: Intersection,
-    end: Intersection,
-    a: bool,
-    b: bool
-}
 
-
-fn get_regions(ray: &Ray, a: &Shape, b: &Shape) -> Vec<Region> {
-    enum Owner {A, B}
-
-    let a_intersections = a.all_intersections(ray);
-    let b_intersections = b.all_intersections(ray);
-
-    let mut intersections = a_intersections.into_iter().flat_map(|(entry, exit)|{
-        vec![(entry, Owner::A), (exit.inverse(), Owner::A)]
-    }).chain(b_intersections.into_iter().flat_map(|(entry, exit)|{
-        vec![(entry, Owner::B), (exit.inverse(), Owner::B)]
-    })).collect::<Vec<_>>();
-
-    intersections.sort_by(|a, b| a.0.distance.partial_cmp(&b.0.distance).unwrap());
-
-    use std::f64::INFINITY;
-    let mut regions = vec![
-        Region {
-            start: Intersection {
-                point: ray.direction * -INFINITY,
-                normal: -ray.direction,
-                distance: -INFINITY,
-            },
-            end: Intersection {
-                point: ray.direction * -INFINITY,
-                normal: -ray.direction,
-                distance: -INFINITY,
-            },
-            a: false,
-            b: false,
-        }
-    ];
-
-    for (i, (intersection, owner)) in intersections.into_iter().enumerate() {
-        let (previous_a, previous_b) = {
-            let previous_region = &mut regions[i];
-
-            previous_region.end = intersection.clone().inverse();
-
-            (previous_region.a, previous_region.b)
-        };
-
-        let (a, b) = match owner {
-            Owner::A => (!previous_a, previous_b),
-            Owner::B => (previous_a, !previous_b),
-        };
-
-        let region = Region {
-            start: intersection.clone(),
-            end: intersection,
-            a,
-            b,
-        };
-
-        regions.push(region);
+    /// Get a random point on the surface and the normal at that point, all points being
+    /// equally likely
+    fn sample_surface(&self) -> Option<(Vector3, Vector3)> {
+        None
     }
 
-    regions.last_mut().map(|region| region.end = Intersection {
-        point: ray.direction * INFINITY,
-        normal: ray.direction,
-        distance: INFINITY,
-    });
+    /// The number of primitives the shape is made of, which composite shapes number from the
+    /// left in the `leaf` of their intersections
+    fn leaf_count(&self) -> usize {
+        1
+    }
 
-    regions
+    /// Return the first entry and exit pair of intersections
+    fn first_intersection(&self, ray: &Ray) -> Option<EntryExit> {
+        self.all_intersections(ray).into_iter().next()
+    }
 }
@@ -5,16 +5,21 @@ use ray::{
     EntryExit
 };
 
+use super::{
+    Shape,
+    Bounds
+};
+
 #[derive(Debug, Clone)]
 pub struct Plane {
     pub origin: Vector3,
     pub normal: Vector3
 }
 
-impl Plane {
+impl Shape for Plane {
     /// Return the first entry and exit pair of intersections
     // https://www.wikiwand.com/en/Line%E2%80%93plane_intersection
-    pub fn intersection(&self, ray: &Ray) -> Vec<EntryExit> {
+    fn all_intersections(&self, ray: &Ray) -> Vec<EntryExit> {
         let direction = Vector3::dot(ray.direction, self.normal);
         let parallel = direction == 0.0;
 
@@ -26,19 +31,26 @@ impl Plane {
 
             if distance > 0.0 {
                 let point = ray.origin + distance * ray.direction;
+                let (tangent, bitangent) = self.axes();
 
                 let entry = Intersection {
                     point,
                     normal: self.normal,
-                    distance
+                    distance,
+                    uv: self.surface_coordinates(point),
+                    tangent,
+                    bitangent,
+                    leaf: 0,
                 };
 
-                use std::f64::INFINITY;
-
                 let exit = Intersection {
-                    point: ray.direction * INFINITY,
+                    point: ray.direction * f64::INFINITY,
                     normal: -self.normal,
-                    distance: INFINITY
+                    distance: f64::INFINITY,
+                    uv: (0.0, 0.0),
+                    tangent,
+                    bitangent,
+                    leaf: 0,
                 };
 
                 vec![(entry, exit)]
@@ -47,4 +59,29 @@ impl Plane {
             }
         }
     }
-}
\ No newline at end of file
+
+    fn bounds(&self) -> Bounds {
+        Bounds::infinite()
+    }
+
+    /// Everything behind the plane is inside it
+    fn contains(&self, point: Vector3) -> bool {
+        Vector3::dot(point - self.origin, self.normal) < 0.0
+    }
+
+    /// The distance from the origin of the plane along its axes
+    fn surface_coordinates(&self, point: Vector3) -> (f64, f64) {
+        let (tangent, bitangent) = self.axes();
+        let offset = point - self.origin;
+
+        (Vector3::dot(offset, tangent), Vector3::dot(offset, bitangent))
+    }
+}
+
+
+impl Plane {
+    /// Two perpendicular directions within the plane
+    pub fn axes(&self) -> (Vector3, Vector3) {
+        self.normal.perpendicular_axes()
+    }
+}
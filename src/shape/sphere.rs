@@ -1,10 +1,18 @@
 use vector::Vector3;
 use ray::Ray;
+use sampler;
 use ray::{
     Intersection,
     EntryExit
 };
 
+use std::f64::consts::PI;
+
+use super::{
+    Shape,
+    Bounds
+};
+
 #[derive(Debug, Clone)]
 pub struct Sphere {
     pub center: Vector3,
@@ -12,9 +20,9 @@ pub struct Sphere {
 }
 
 
-impl Sphere {
+impl Shape for Sphere {
     /// Returns the entry and exit points of a ray respectively
-    pub fn intersection(&self, ray: &Ray) -> Vec<EntryExit> {
+    fn all_intersections(&self, ray: &Ray) -> Vec<EntryExit> {
         let projection = Vector3::dot(self.center - ray.origin, ray.direction);
 
         // The point on the ray closest to the center of the sphere
@@ -39,10 +47,118 @@ impl Sphere {
         }
     }
 
+    fn bounds(&self) -> Bounds {
+        let radius = Vector3::new(self.radius, self.radius, self.radius);
+        Bounds::new(self.center - radius, self.center + radius)
+    }
+
+    fn contains(&self, point: Vector3) -> bool {
+        Vector3::distance(point, self.center) < self.radius
+    }
+
+    /// Longitude and latitude, both in the range [0, 1]
+    fn surface_coordinates(&self, point: Vector3) -> (f64, f64) {
+        longitude_latitude((point - self.center).normal())
+    }
+
+    fn surface_area(&self) -> Option<f64> {
+        Some(4.0 * PI * self.radius * self.radius)
+    }
+
+    // https://mathworld.wolfram.com/SpherePointPicking.html
+    fn sample_surface(&self) -> Option<(Vector3, Vector3)> {
+        let z = sampler::gen_range(-1.0, 1.0);
+        let angle = sampler::gen_range(0.0, 2.0 * PI);
+        let radius = (1.0 - z * z).sqrt();
+
+        let normal = Vector3::new(radius * angle.cos(), radius * angle.sin(), z);
+
+        Some((self.center + self.radius * normal, normal))
+    }
+}
+
+
+impl Sphere {
     fn intersection_along_ray(&self, distance: f64, ray: &Ray) -> Intersection {
         let point = ray.origin + distance * ray.direction;
         let normal = Vector3::normal(point - self.center);
-        Intersection { point, normal, distance }
+
+        // The tangent follows the lines of latitude, which shrink to a point at the poles. A
+        // unit of `u` goes once around the sphere, and a unit of `v` from pole to pole.
+        let tangent = Vector3::new(-normal.z, 0.0, normal.x);
+        let (tangent, bitangent) = if tangent.length() > 1.0e-9 {
+            let bitangent = Vector3::cross(tangent.normal(), normal);
+            (2.0 * PI * self.radius * tangent, PI * self.radius * bitangent)
+        } else {
+            normal.perpendicular_axes()
+        };
+
+        Intersection {
+            point,
+            normal,
+            distance,
+            uv: longitude_latitude(normal),
+            tangent,
+            bitangent,
+            leaf: 0,
+        }
     }
 }
 
+
+/// The longitude and latitude of a direction from the center, both in the range [0, 1]
+fn longitude_latitude(normal: Vector3) -> (f64, f64) {
+    let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+    let v = 0.5 + normal.y.clamp(-1.0, 1.0).asin() / PI;
+
+    (u, v)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersections_have_the_surface_coordinates_of_their_point() {
+        let sphere = Sphere {
+            center: Vector3::new(1.0, 2.0, 3.0),
+            radius: 2.0,
+        };
+
+        let ray = Ray {
+            origin: Vector3::new(-4.0, 2.5, 3.5),
+            direction: Vector3::new(1.0, 0.1, -0.1).normal(),
+        };
+
+        let (entry, exit) = sphere.first_intersection(&ray).unwrap();
+
+        for intersection in &[entry, exit] {
+            let (u, v) = sphere.surface_coordinates(intersection.point);
+            assert!((intersection.uv.0 - u).abs() < 1.0e-9);
+            assert!((intersection.uv.1 - v).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn inverse_keeps_the_tangents() {
+        let sphere = Sphere {
+            center: Vector3::zero(),
+            radius: 1.0,
+        };
+
+        let ray = Ray {
+            origin: Vector3::new(0.3, 0.2, -5.0),
+            direction: Vector3::new(0.0, 0.0, 1.0),
+        };
+
+        let (entry, _) = sphere.first_intersection(&ray).unwrap();
+        let inverse = entry.clone().inverse();
+
+        assert!((entry.normal + inverse.normal).length() < 1.0e-9);
+        assert!((entry.tangent - inverse.tangent).length() < 1.0e-9);
+        assert!((entry.bitangent - inverse.bitangent).length() < 1.0e-9);
+        assert_eq!(entry.uv, inverse.uv);
+    }
+}
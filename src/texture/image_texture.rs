@@ -0,0 +1,153 @@
+use image::{
+    self,
+    RgbImage
+};
+
+use color::Color;
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{
+    Texture,
+    SurfacePoint,
+    Mapping
+};
+
+/// An image covering the unit square of texture coordinates, with `v` going from the bottom
+/// of the image to the top. Colors between pixels are interpolated bilinearly.
+#[derive(Clone)]
+pub struct ImageTexture {
+    image: Arc<RgbImage>,
+
+    pub wrap: Wrap,
+    pub mapping: Mapping
+}
+
+/// What a texture shows outside of the unit square
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wrap {
+    /// The image repeats
+    Repeat,
+
+    /// The image repeats, with every other copy mirrored so that the edges match up
+    Mirror,
+
+    /// The pixels at the edge of the image extend outwards
+    Clamp
+}
+
+
+impl ImageTexture {
+    /// Create a texture from an image, which must not be empty
+    pub fn new(image: RgbImage) -> ImageTexture {
+        assert!(image.width() > 0 && image.height() > 0, "a texture needs at least one pixel");
+
+        ImageTexture {
+            image: Arc::new(image),
+            wrap: Wrap::Repeat,
+            mapping: Mapping::default(),
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> image::ImageResult<ImageTexture> {
+        let image = image::open(path)?.to_rgb();
+
+        if image.width() == 0 || image.height() == 0 {
+            return Err(image::ImageError::DimensionError);
+        }
+
+        Ok(ImageTexture::new(image))
+    }
+
+    /// Get the color at a point of the texture
+    pub fn sample(&self, u: f64, v: f64) -> Color {
+        let (width, height) = self.image.dimensions();
+
+        // Pixel centers lie halfway between whole coordinates
+        let x = u * width as f64 - 0.5;
+        let y = (1.0 - v) * height as f64 - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.pixel(x0, y0).mix(self.pixel(x0 + 1, y0), dx);
+        let bottom = self.pixel(x0, y0 + 1).mix(self.pixel(x0 + 1, y0 + 1), dx);
+
+        top.mix(bottom, dy)
+    }
+
+    fn pixel(&self, x: i64, y: i64) -> Color {
+        let (width, height) = self.image.dimensions();
+        let x = self.wrap.index(x, width);
+        let y = self.wrap.index(y, height);
+
+        (*self.image.get_pixel(x, y)).into()
+    }
+}
+
+impl Texture for ImageTexture {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let (u, v) = self.mapping.coordinates(point);
+        self.sample(u, v)
+    }
+}
+
+impl fmt::Debug for ImageTexture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (width, height) = self.image.dimensions();
+        write!(f, "ImageTexture({}x{}, {:?}, {:?})", width, height, self.wrap, self.mapping)
+    }
+}
+
+
+impl Wrap {
+    /// Map a pixel index, which may lie outside of the image, into the image
+    fn index(self, index: i64, size: u32) -> u32 {
+        let size = i64::from(size);
+
+        let index = match self {
+            Wrap::Repeat => index.rem_euclid(size),
+            Wrap::Mirror => {
+                let index = index.rem_euclid(2 * size);
+
+                if index < size {
+                    index
+                } else {
+                    2 * size - 1 - index
+                }
+            },
+            Wrap::Clamp => index.clamp(0, size - 1),
+        };
+
+        index as u32
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic]
+    fn empty_texture_is_rejected() {
+        ImageTexture::new(RgbImage::new(4, 0));
+    }
+
+    #[test]
+    fn single_pixel_texture_is_that_color_everywhere() {
+        let mut texture = ImageTexture::new(RgbImage::from_pixel(1, 1, image::Rgb([255, 0, 0])));
+
+        for &wrap in &[Wrap::Repeat, Wrap::Mirror, Wrap::Clamp] {
+            texture.wrap = wrap;
+
+            for &(u, v) in &[(0.0, 0.0), (0.5, 0.5), (-3.2, 7.9)] {
+                let color = texture.sample(u, v);
+                assert!(color.r > 0.99 && color.g == 0.0 && color.b == 0.0);
+            }
+        }
+    }
+}
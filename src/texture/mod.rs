@@ -0,0 +1,128 @@
+mod image_texture;
+mod pattern;
+mod noise;
+mod normal_map;
+mod solid;
+
+pub use self::image_texture::{
+    ImageTexture,
+    Wrap
+};
+pub use self::pattern::{
+    Checker,
+    Stripes,
+    Grid
+};
+pub use self::noise::{
+    PerlinNoise,
+    Noise,
+    perlin,
+    simplex,
+    worley
+};
+pub use self::normal_map::NormalMap;
+pub use self::solid::{
+    SolidNoise,
+    Cells,
+    Marble,
+    Wood
+};
+
+use vector::Vector3;
+use color::Color;
+
+use std::fmt::Debug;
+
+/// A color or value that varies over the surface of an object
+pub trait Texture: Debug + Send + Sync {
+    fn color(&self, point: &SurfacePoint) -> Color;
+
+    /// Get a single value, for material parameters that are not colors
+    fn value(&self, point: &SurfacePoint) -> f64 {
+        self.color(point).luminance()
+    }
+}
+
+/// A point on the surface of an object, at which textures are evaluated
+#[derive(Debug, Clone, Copy)]
+pub struct SurfacePoint {
+    pub position: Vector3,
+
+    /// The position relative to the center of the object, at which solid textures are
+    /// evaluated so that they move along with the object
+    pub object_position: Vector3,
+
+    pub normal: Vector3,
+
+    /// The surface coordinates of the point
+    pub uv: (f64, f64),
+
+    /// How the point moves along the surface as `u` and `v` increase, see `Intersection`
+    pub tangent: Vector3,
+    pub bitangent: Vector3
+}
+
+/// How points on a surface are mapped onto a two-dimensional texture
+#[derive(Debug, Clone, Copy)]
+pub enum Mapping {
+    /// The surface coordinates of the object, multiplied by a scale
+    Uv {
+        scale_u: f64,
+        scale_v: f64
+    },
+
+    /// The position of the point, projected onto a plane spanned by two axes, for example to
+    /// project a texture onto objects from the front. Each axis spans one unit of the texture.
+    Planar {
+        origin: Vector3,
+        u_axis: Vector3,
+        v_axis: Vector3
+    }
+}
+
+
+impl Texture for Color {
+    fn color(&self, _point: &SurfacePoint) -> Color {
+        *self
+    }
+}
+
+impl Texture for f64 {
+    fn color(&self, _point: &SurfacePoint) -> Color {
+        Color::new(*self, *self, *self)
+    }
+
+    fn value(&self, _point: &SurfacePoint) -> f64 {
+        *self
+    }
+}
+
+
+impl Default for Mapping {
+    fn default() -> Mapping {
+        Mapping::Uv {
+            scale_u: 1.0,
+            scale_v: 1.0,
+        }
+    }
+}
+
+impl Mapping {
+    /// Get the texture coordinates of a point
+    pub fn coordinates(&self, point: &SurfacePoint) -> (f64, f64) {
+        match *self {
+            Mapping::Uv { scale_u, scale_v } => {
+                let (u, v) = point.uv;
+                (u * scale_u, v * scale_v)
+            },
+            Mapping::Planar { origin, u_axis, v_axis } => {
+                let offset = point.position - origin;
+
+                (
+                    Vector3::dot(offset, u_axis) / Vector3::dot(u_axis, u_axis),
+                    Vector3::dot(offset, v_axis) / Vector3::dot(v_axis, v_axis)
+                )
+            },
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use vector::Vector3;
+use color::Color;
+
+use super::{
+    Texture,
+    SurfacePoint,
+    Mapping
+};
+
+/// Smooth random variation between two colors, with features about one unit of the texture
+/// coordinates wide
+#[derive(Debug, Clone)]
+pub struct PerlinNoise {
+    pub low: Color,
+    pub high: Color,
+    pub mapping: Mapping
+}
+
+/// The basic kinds of noise that solid textures are built from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Noise {
+    Perlin,
+    Simplex,
+
+    /// Cellular noise, from `worley`
+    Worley
+}
+
+
+impl Texture for PerlinNoise {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let (u, v) = self.mapping.coordinates(point);
+        let noise = perlin(Vector3::new(u, v, 0.0));
+
+        self.low.mix(self.high, (0.5 + 0.5 * noise).clamp(0.0, 1.0))
+    }
+}
+
+
+impl Noise {
+    /// Get the noise at a point, roughly in the range [-1, 1]
+    pub fn value(self, point: Vector3) -> f64 {
+        match self {
+            Noise::Perlin => perlin(point),
+            Noise::Simplex => simplex(point),
+            Noise::Worley => 2.0 * worley(point) - 1.0,
+        }
+    }
+
+    /// Fractional Brownian motion: layers of noise, each with twice the frequency and half the
+    /// amplitude of the last, roughly in the range [-1, 1]
+    pub fn fbm(self, point: Vector3, octaves: u32) -> f64 {
+        self.layers(point, octaves, |noise| noise)
+    }
+
+    /// Like fBm, but adding up the absolute value of each layer, which gives sharp creases.
+    /// It is roughly in the range [0, 1].
+    pub fn turbulence(self, point: Vector3, octaves: u32) -> f64 {
+        self.layers(point, octaves, f64::abs)
+    }
+
+    fn layers<F>(self, point: Vector3, octaves: u32, layer: F) -> f64
+        where F: Fn(f64) -> f64 {
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..octaves {
+            sum += amplitude * layer(self.value(point * frequency));
+            total_amplitude += amplitude;
+
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+
+        if total_amplitude > 0.0 {
+            sum / total_amplitude
+        } else {
+            0.0
+        }
+    }
+}
+
+
+/// Improved Perlin noise, roughly in the range [-1, 1]. It is 0 at every whole coordinate.
+// https://mrl.cs.nyu.edu/~perlin/noise/
+pub fn perlin(point: Vector3) -> f64 {
+    let (x0, y0, z0) = (point.x.floor(), point.y.floor(), point.z.floor());
+    let (x, y, z) = (point.x - x0, point.y - y0, point.z - z0);
+    let (x0, y0, z0) = (x0 as i64, y0 as i64, z0 as i64);
+
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        gradient(hash(x0 + dx, y0 + dy, z0 + dz), x - dx as f64, y - dy as f64, z - dz as f64)
+    };
+
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0))
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1))
+        )
+    )
+}
+
+/// Simplex noise, roughly in the range [-1, 1]. It has fewer directional artifacts than
+/// Perlin noise.
+// http://staffwww.itn.liu.se/~stegu/simplexnoise/simplexnoise.pdf
+pub fn simplex(point: Vector3) -> f64 {
+    const SKEW: f64 = 1.0 / 3.0;
+    const UNSKEW: f64 = 1.0 / 6.0;
+
+    // Find the cell of the skewed lattice, and the offset from its first corner
+    let skew = (point.x + point.y + point.z) * SKEW;
+    let (i, j, k) = ((point.x + skew).floor(), (point.y + skew).floor(), (point.z + skew).floor());
+
+    let unskew = (i + j + k) * UNSKEW;
+    let (x, y, z) = (point.x - i + unskew, point.y - j + unskew, point.z - k + unskew);
+    let (i, j, k) = (i as i64, j as i64, k as i64);
+
+    // The order of the coordinates decides which of the six simplices in the cell contains
+    // the point
+    let (second, third) = if x >= y {
+        if y >= z {
+            ((1, 0, 0), (1, 1, 0))
+        } else if x >= z {
+            ((1, 0, 0), (1, 0, 1))
+        } else {
+            ((0, 0, 1), (1, 0, 1))
+        }
+    } else if y < z {
+        ((0, 0, 1), (0, 1, 1))
+    } else if x < z {
+        ((0, 1, 0), (0, 1, 1))
+    } else {
+        ((0, 1, 0), (1, 1, 0))
+    };
+
+    let corners = [(0, 0, 0), second, third, (1, 1, 1)];
+
+    let sum = corners.iter().enumerate().map(|(n, &(di, dj, dk))| {
+        let x = x - di as f64 + n as f64 * UNSKEW;
+        let y = y - dj as f64 + n as f64 * UNSKEW;
+        let z = z - dk as f64 + n as f64 * UNSKEW;
+
+        let falloff = 0.6 - x * x - y * y - z * z;
+
+        if falloff > 0.0 {
+            falloff.powi(4) * gradient(hash(i + di, j + dj, k + dk), x, y, z)
+        } else {
+            0.0
+        }
+    }).sum::<f64>();
+
+    32.0 * sum
+}
+
+/// The distance to the closest of a set of random points, one in every cell of the integer
+/// lattice. It is mostly in the range [0, 1].
+// https://www.wikiwand.com/en/Worley_noise
+pub fn worley(point: Vector3) -> f64 {
+    let (x, y, z) = (point.x.floor() as i64, point.y.floor() as i64, point.z.floor() as i64);
+
+    let mut closest = f64::INFINITY;
+
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            for dz in -1..=1 {
+                let (cx, cy, cz) = (x + dx, y + dy, z + dz);
+                let hash = hash(cx, cy, cz);
+
+                // Use three parts of the hash as the position within the cell
+                let part = |shift: u32| ((hash >> shift) & 0x1f_ffff) as f64 / f64::from(0x20_0000);
+                let feature = Vector3::new(
+                    cx as f64 + part(0),
+                    cy as f64 + part(21),
+                    cz as f64 + part(42)
+                );
+
+                closest = closest.min(Vector3::distance(point, feature));
+            }
+        }
+    }
+
+    closest
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// The dot product of an offset with one of twelve gradients towards the edges of a cube,
+/// chosen by a hash
+fn gradient(hash: u64, x: f64, y: f64, z: f64) -> f64 {
+    let hash = hash & 15;
+
+    let u = if hash < 8 { x } else { y };
+    let v = if hash < 4 {
+        y
+    } else if hash == 12 || hash == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if hash & 1 == 0 { u } else { -u }) + (if hash & 2 == 0 { v } else { -v })
+}
+
+/// Hash a point of the integer lattice, instead of looking it up in a permutation table, so
+/// that the noise does not repeat
+fn hash(x: i64, y: i64, z: i64) -> u64 {
+    let mut hash = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^
+        (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f) ^
+        (z as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+
+    // https://xorshift.di.unimi.it/splitmix64.c
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
@@ -0,0 +1,194 @@
+use vector::Vector3;
+
+use std::sync::Arc;
+
+use super::{
+    Texture,
+    SurfacePoint
+};
+
+/// Changes the direction a surface faces when it is shaded, to add detail without changing
+/// its shape
+#[derive(Debug, Clone)]
+pub enum NormalMap {
+    /// A texture whose red, green and blue channels give the normal along the tangent,
+    /// bitangent and normal of the surface, mapped from [-1, 1] to [0, 1]. The strength scales
+    /// how far the normal leans away from the surface normal.
+    TangentSpace {
+        texture: Arc<dyn Texture>,
+        strength: f64
+    },
+
+    /// A texture giving the height of the surface, multiplied by the strength
+    Bump {
+        texture: Arc<dyn Texture>,
+        strength: f64
+    }
+}
+
+/// The distance along the surface used to find the slope of a bump texture
+const BUMP_STEP: f64 = 1.0e-3;
+
+
+impl NormalMap {
+    pub fn tangent_space<T: Texture + 'static>(texture: T, strength: f64) -> NormalMap {
+        NormalMap::TangentSpace {
+            texture: Arc::new(texture),
+            strength,
+        }
+    }
+
+    pub fn bump<T: Texture + 'static>(texture: T, strength: f64) -> NormalMap {
+        NormalMap::Bump {
+            texture: Arc::new(texture),
+            strength,
+        }
+    }
+
+    /// Get the normal used for shading a point. It always faces the same side as the
+    /// surface normal.
+    pub fn normal(&self, point: &SurfacePoint) -> Vector3 {
+        let normal = match *self {
+            NormalMap::TangentSpace { ref texture, strength } => {
+                let color = texture.color(point);
+
+                let x = (2.0 * color.r - 1.0) * strength;
+                let y = (2.0 * color.g - 1.0) * strength;
+                let z = 2.0 * color.b - 1.0;
+
+                x * point.tangent.normal() + y * point.bitangent.normal() + z * point.normal
+            },
+            NormalMap::Bump { ref texture, strength } => {
+                // The texture is sampled a short distance away along the tangent and bitangent,
+                // moving the surface coordinates by as much as that distance covers, so that
+                // the slope does not depend on how large the coordinates are on the surface
+                let height = texture.value(point);
+                let slope = |axis: Vector3, du: f64, dv: f64| {
+                    let length = axis.length();
+                    if length < 1.0e-9 {
+                        return Vector3::zero();
+                    }
+
+                    let (u, v) = point.uv;
+                    let (du, dv) = (du * BUMP_STEP / length, dv * BUMP_STEP / length);
+                    let offset = du * point.tangent + dv * point.bitangent;
+
+                    let point = SurfacePoint {
+                        position: point.position + offset,
+                        object_position: point.object_position + offset,
+                        uv: (u + du, v + dv),
+                        ..*point
+                    };
+
+                    (texture.value(&point) - height) / BUMP_STEP * (axis / length)
+                };
+
+                let gradient = slope(point.tangent, 1.0, 0.0) + slope(point.bitangent, 0.0, 1.0);
+
+                point.normal - strength * gradient
+            },
+        };
+
+        if Vector3::dot(normal, point.normal) > 0.0 {
+            normal.normal()
+        } else {
+            point.normal
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color::Color;
+    use ray::Ray;
+    use shape::{
+        Shape,
+        Sphere,
+        difference
+    };
+
+    /// A height that rises steadily along the x-axis
+    #[derive(Debug)]
+    struct Ramp;
+
+    impl Texture for Ramp {
+        fn color(&self, point: &SurfacePoint) -> Color {
+            let height = self.value(point);
+            Color::new(height, height, height)
+        }
+
+        fn value(&self, point: &SurfacePoint) -> f64 {
+            0.5 * point.position.x
+        }
+    }
+
+    /// A height equal to the `u` coordinate of the surface
+    #[derive(Debug)]
+    struct UvRamp;
+
+    impl Texture for UvRamp {
+        fn color(&self, point: &SurfacePoint) -> Color {
+            let height = self.value(point);
+            Color::new(height, height, height)
+        }
+
+        fn value(&self, point: &SurfacePoint) -> f64 {
+            point.uv.0
+        }
+    }
+
+    #[test]
+    fn bumps_on_cut_faces_lean_away_from_the_slope() {
+        let shape = difference(
+            Sphere { center: Vector3::zero(), radius: 3.0 },
+            Sphere { center: Vector3::new(0.0, 0.0, -3.0), radius: 2.0 }
+        );
+
+        let ray = Ray {
+            origin: Vector3::new(0.3, 0.1, -10.0),
+            direction: Vector3::new(0.0, 0.0, 1.0),
+        };
+
+        let (entry, _) = shape.first_intersection(&ray).unwrap();
+
+        let point = SurfacePoint {
+            position: entry.point,
+            object_position: entry.point,
+            normal: entry.normal,
+            uv: entry.uv,
+            tangent: entry.tangent,
+            bitangent: entry.bitangent,
+        };
+
+        // The height only changes along the tangent, by one per unit of `u`
+        let gradient = entry.tangent / Vector3::dot(entry.tangent, entry.tangent);
+        let expected = (entry.normal - gradient).normal();
+        let normal = NormalMap::bump(UvRamp, 1.0).normal(&point);
+
+        assert!((normal - expected).length() < 1.0e-3, "{:?} != {:?}", normal, expected);
+        assert!(normal.x > entry.normal.x);
+    }
+
+    #[test]
+    fn bump_slope_does_not_depend_on_the_scale_of_the_coordinates() {
+        let map = NormalMap::bump(Ramp, 1.0);
+
+        for &(scale_u, scale_v) in &[(1.0, 1.0), (5.0, 3.0), (0.01, 200.0)] {
+            let point = SurfacePoint {
+                position: Vector3::new(1.0, 0.0, 2.0),
+                object_position: Vector3::new(1.0, 0.0, 2.0),
+                normal: Vector3::new(0.0, 1.0, 0.0),
+                uv: (0.3, 0.7),
+                tangent: Vector3::new(scale_u, 0.0, 0.0),
+                bitangent: Vector3::new(0.0, 0.0, scale_v),
+            };
+
+            let expected = Vector3::new(-0.5, 1.0, 0.0).normal();
+            let normal = map.normal(&point);
+
+            assert!((normal - expected).length() < 1.0e-6, "{:?} at scale {}", normal, scale_u);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use color::Color;
+
+use super::{
+    Texture,
+    SurfacePoint,
+    Mapping
+};
+
+/// Squares of two alternating colors, each one unit of the texture coordinates wide
+#[derive(Debug, Clone)]
+pub struct Checker {
+    pub a: Color,
+    pub b: Color,
+    pub mapping: Mapping
+}
+
+/// Stripes of two alternating colors running along the `v` direction, each one unit of the
+/// texture coordinates wide
+#[derive(Debug, Clone)]
+pub struct Stripes {
+    pub a: Color,
+    pub b: Color,
+    pub mapping: Mapping
+}
+
+/// Lines along every whole texture coordinate
+#[derive(Debug, Clone)]
+pub struct Grid {
+    pub line: Color,
+    pub background: Color,
+
+    /// The width of the lines, relative to the size of a cell
+    pub line_width: f64,
+
+    pub mapping: Mapping
+}
+
+
+impl Texture for Checker {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let (u, v) = self.mapping.coordinates(point);
+
+        if is_even(u.floor() + v.floor()) {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+impl Texture for Stripes {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let (u, _) = self.mapping.coordinates(point);
+
+        if is_even(u.floor()) {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+impl Texture for Grid {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let (u, v) = self.mapping.coordinates(point);
+
+        // The distance to the closest line in each direction
+        let distance_u = (u - u.round()).abs();
+        let distance_v = (v - v.round()).abs();
+
+        if distance_u.min(distance_v) < self.line_width / 2.0 {
+            self.line
+        } else {
+            self.background
+        }
+    }
+}
+
+
+fn is_even(value: f64) -> bool {
+    value.rem_euclid(2.0) < 1.0
+}
@@ -0,0 +1,134 @@
+use color::Color;
+
+use std::f64::consts::PI;
+
+use super::{
+    Texture,
+    SurfacePoint,
+    Noise,
+    worley
+};
+
+/// Fractal noise through the volume of an object, blending between two colors
+#[derive(Debug, Clone)]
+pub struct SolidNoise {
+    pub low: Color,
+    pub high: Color,
+
+    /// The size of the largest features
+    pub scale: f64,
+
+    pub noise: Noise,
+    pub octaves: u32,
+
+    /// Use turbulence, with sharp creases, instead of fBm
+    pub turbulent: bool
+}
+
+/// Cells around randomly scattered points, shaded from one color at their centers to another
+/// at their edges
+#[derive(Debug, Clone)]
+pub struct Cells {
+    pub center: Color,
+    pub edge: Color,
+
+    /// The average distance between the cells
+    pub scale: f64
+}
+
+/// Veins of one color through another, running across the x-axis and distorted by turbulence
+#[derive(Debug, Clone)]
+pub struct Marble {
+    pub base: Color,
+    pub vein: Color,
+
+    /// The distance between the veins
+    pub scale: f64,
+
+    /// How far the veins are pushed around
+    pub turbulence: f64
+}
+
+/// Growth rings around the y-axis through the center of the object
+#[derive(Debug, Clone)]
+pub struct Wood {
+    pub light: Color,
+    pub dark: Color,
+
+    pub ring_width: f64,
+
+    /// How far the rings are pushed around, relative to their width
+    pub turbulence: f64
+}
+
+
+impl Texture for SolidNoise {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let position = point.object_position / self.scale;
+
+        let value = if self.turbulent {
+            self.noise.turbulence(position, self.octaves)
+        } else {
+            0.5 + 0.5 * self.noise.fbm(position, self.octaves)
+        };
+
+        self.low.mix(self.high, value.clamp(0.0, 1.0))
+    }
+}
+
+impl Texture for Cells {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let distance = worley(point.object_position / self.scale);
+        self.center.mix(self.edge, distance.min(1.0))
+    }
+}
+
+impl Texture for Marble {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let position = point.object_position / self.scale;
+        let turbulence = Noise::Perlin.turbulence(position, 6);
+
+        let wave = (PI * (position.x + self.turbulence * turbulence)).sin();
+
+        // The veins are the thin troughs of the wave
+        self.base.mix(self.vein, (0.5 - 0.5 * wave).powi(6))
+    }
+}
+
+impl Texture for Wood {
+    fn color(&self, point: &SurfacePoint) -> Color {
+        let position = point.object_position / self.ring_width;
+        let radius = (position.x * position.x + position.z * position.z).sqrt();
+
+        let distortion = self.turbulence * Noise::Perlin.fbm(position / 4.0, 3);
+        let ring = (radius + distortion).rem_euclid(1.0);
+
+        // Each ring grows light at first and darkens towards its edge
+        self.light.mix(self.dark, ring.powi(3))
+    }
+}
+
+
+impl Default for Marble {
+    /// White marble with grey veins
+    fn default() -> Marble {
+        Marble {
+            base: Color::new(0.9, 0.9, 0.87),
+            vein: Color::new(0.3, 0.3, 0.35),
+            scale: 0.5,
+            turbulence: 4.0,
+        }
+    }
+}
+
+impl Default for Wood {
+    /// Light oak
+    fn default() -> Wood {
+        Wood {
+            light: Color::new(0.76, 0.56, 0.33),
+            dark: Color::new(0.45, 0.27, 0.12),
+            ring_width: 0.08,
+            turbulence: 0.6,
+        }
+    }
+}
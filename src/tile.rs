@@ -0,0 +1,195 @@
+use std::str::FromStr;
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering
+};
+
+/// A rectangular region of the image that is rendered as a single unit of work
+#[derive(Debug, Copy, Clone)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32
+}
+
+/// The order in which tiles are rendered
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TileOrder {
+    /// Row by row, from the top left corner
+    Scanline,
+
+    /// Along a Hilbert curve, which keeps consecutive tiles close to each other
+    Hilbert,
+
+    /// Outwards from the center of the image
+    Spiral,
+}
+
+
+impl Tile {
+    /// Iterate over the pixels of the tile, row by row
+    pub fn pixels(&self) -> impl Iterator<Item=(u32, u32)> {
+        let tile = *self;
+        (tile.y..tile.y + tile.height)
+            .flat_map(move |y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+    }
+
+    pub fn area(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+}
+
+
+/// Hands out tiles to the render threads, each tile exactly once
+#[derive(Debug)]
+pub struct TileQueue {
+    tiles: Vec<Tile>,
+    next: AtomicUsize
+}
+
+impl TileQueue {
+    pub fn new(tiles: Vec<Tile>) -> TileQueue {
+        TileQueue {
+            tiles,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn next(&self) -> Option<Tile> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        self.tiles.get(index).cloned()
+    }
+}
+
+
+/// Split an image into tiles of at most `size` pixels squared, sorted in the given order
+pub fn get_tiles(width: u32, height: u32, size: u32, order: TileOrder) -> Vec<Tile> {
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+
+    let mut cells = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            cells.push((column, row));
+        }
+    }
+
+    match order {
+        TileOrder::Scanline => {},
+        TileOrder::Hilbert => {
+            let side = columns.max(rows).next_power_of_two();
+            cells.sort_by_key(|&(column, row)| hilbert_index(side, column, row));
+        },
+        TileOrder::Spiral => {
+            let center_x = (columns as f64 - 1.0) / 2.0;
+            let center_y = (rows as f64 - 1.0) / 2.0;
+
+            let ring_and_angle = |&(column, row): &(u32, u32)| {
+                let dx = column as f64 - center_x;
+                let dy = row as f64 - center_y;
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+
+            cells.sort_by(|a, b| ring_and_angle(a).partial_cmp(&ring_and_angle(b)).unwrap());
+        },
+    }
+
+    cells.into_iter()
+        .map(|(column, row)| {
+            let x = column * size;
+            let y = row * size;
+
+            Tile {
+                x,
+                y,
+                width: size.min(width - x),
+                height: size.min(height - y),
+            }
+        })
+        .collect()
+}
+
+
+// https://www.wikiwand.com/en/Hilbert_curve
+fn hilbert_index(side: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+
+        index += u64::from(s) * u64::from(s) * u64::from((3 * rx) ^ ry);
+
+        // Rotate the quadrant so that the curve stays continuous
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+
+            ::std::mem::swap(&mut x, &mut y);
+        }
+
+        s /= 2;
+    }
+
+    index
+}
+
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<TileOrder, String> {
+        match text {
+            "scanline" => Ok(TileOrder::Scanline),
+            "hilbert" => Ok(TileOrder::Hilbert),
+            "spiral" => Ok(TileOrder::Spiral),
+            _ => Err(format!("Unknown tile order: {}", text)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: [TileOrder; 3] = [TileOrder::Scanline, TileOrder::Hilbert, TileOrder::Spiral];
+
+    #[test]
+    fn every_order_covers_every_pixel_once() {
+        for &order in &ORDERS {
+            for &(width, height, size) in &[(64, 64, 16), (100, 37, 16), (5, 300, 7), (1, 1, 32)] {
+                let mut covered = vec![0; (width * height) as usize];
+
+                for tile in get_tiles(width, height, size, order) {
+                    assert!(tile.width > 0 && tile.height > 0);
+
+                    for (x, y) in tile.pixels() {
+                        covered[(y * width + x) as usize] += 1;
+                    }
+                }
+
+                assert!(
+                    covered.iter().all(|&count| count == 1),
+                    "{:?} does not cover {}x{} with tiles of {} exactly once",
+                    order, width, height, size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hilbert_order_moves_to_a_neighbouring_tile() {
+        let tiles = get_tiles(128, 128, 16, TileOrder::Hilbert);
+
+        for pair in tiles.windows(2) {
+            let distance = (pair[0].x as i64 - pair[1].x as i64).abs() +
+                (pair[0].y as i64 - pair[1].y as i64).abs();
+            assert_eq!(distance, 16);
+        }
+    }
+}
@@ -50,6 +50,22 @@ impl Vector3 {
             z: self.x * other.y - self.y * other.x,
         }
     }
+
+    /// Two unit vectors that are perpendicular to this vector and to each other
+    pub fn perpendicular_axes(self) -> (Vector3, Vector3) {
+        let normal = self.normal();
+
+        let helper = if normal.x.abs() < 0.9 {
+            Vector3::new(1.0, 0.0, 0.0)
+        } else {
+            Vector3::new(0.0, 1.0, 0.0)
+        };
+
+        let tangent = Vector3::cross(helper, normal).normal();
+        let bitangent = Vector3::cross(normal, tangent);
+
+        (tangent, bitangent)
+    }
 }
 
 impl ops::Add<Vector3> for Vector3 {